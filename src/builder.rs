@@ -0,0 +1,310 @@
+use std::{any::Any, sync::Arc, thread, time::Duration};
+
+use crate::{
+    events::EventHandler, DequeueStrategy, DrainMode, ErrorHandler, PoolCreationError, PoolEvent,
+    QueueBackend, ResultMeta, ResultObserver, ThreadPool, ThreadPriority,
+};
+
+/// Configures and creates a [`ThreadPool`].
+pub struct ThreadPoolBuilder {
+    pub(crate) thread_count: usize,
+    pub(crate) detach_on_shutdown_timeout: bool,
+    pub(crate) result_buffer: Option<usize>,
+    pub(crate) catch_panics: bool,
+    pub(crate) reserved_fast_workers: usize,
+    pub(crate) worker_init: Option<Arc<dyn Fn(usize) + Send + Sync>>,
+    pub(crate) respawn_workers: bool,
+    pub(crate) reject_if_no_workers: bool,
+    pub(crate) prewarm: bool,
+    pub(crate) error_handler: Option<ErrorHandler>,
+    pub(crate) result_observer: Option<ResultObserver>,
+    pub(crate) drain_mode: DrainMode,
+    pub(crate) event_handler: Option<EventHandler>,
+    pub(crate) thread_priority: ThreadPriority,
+    pub(crate) default_job_timeout: Option<Duration>,
+    pub(crate) queue_capacity: Option<usize>,
+    pub(crate) verbose: bool,
+    pub(crate) max_job_size: Option<usize>,
+    pub(crate) max_queued_bytes: Option<usize>,
+    pub(crate) queue_backend: Option<Box<dyn QueueBackend>>,
+    pub(crate) dequeue_strategy: DequeueStrategy,
+    pub(crate) deterministic: bool,
+    pub(crate) recycle_job_boxes: bool,
+}
+
+impl ThreadPoolBuilder {
+    /// Creates a new ThreadPoolBuilder.
+    ///
+    /// The thread count defaults to the available parallelism of the machine, or 1 if that
+    /// can't be determined.
+    pub fn new() -> ThreadPoolBuilder {
+        ThreadPoolBuilder {
+            thread_count: thread::available_parallelism().map_or(1, |count| count.get()),
+            detach_on_shutdown_timeout: false,
+            result_buffer: None,
+            catch_panics: true,
+            reserved_fast_workers: 0,
+            worker_init: None,
+            respawn_workers: true,
+            reject_if_no_workers: false,
+            prewarm: false,
+            error_handler: None,
+            result_observer: None,
+            drain_mode: DrainMode::All,
+            event_handler: None,
+            thread_priority: ThreadPriority::Normal,
+            default_job_timeout: None,
+            queue_capacity: None,
+            verbose: false,
+            max_job_size: None,
+            max_queued_bytes: None,
+            queue_backend: None,
+            dequeue_strategy: DequeueStrategy::SharedQueue,
+            deterministic: false,
+            recycle_job_boxes: false,
+        }
+    }
+
+    /// * `thread_count` - Number of threads in the pool.
+    pub fn num_threads(mut self, thread_count: usize) -> ThreadPoolBuilder {
+        self.thread_count = thread_count;
+        self
+    }
+
+    /// * `detach` - Whether [`ThreadPool::shutdown_timeout`] should abandon workers that are
+    ///   still busy once the timeout elapses instead of waiting for them.
+    ///
+    /// Abandoned threads are leaked and keep running until their job returns.
+    pub fn detach_on_shutdown_timeout(mut self, detach: bool) -> ThreadPoolBuilder {
+        self.detach_on_shutdown_timeout = detach;
+        self
+    }
+
+    /// * `capacity` - Number of results that can wait in a
+    ///   [`result_channel`](ThreadPool::result_channel) before workers block on delivery.
+    ///
+    /// Bounding the buffer ties job throughput to the speed of the consumer, so a slow consumer
+    /// can't make results pile up in memory.
+    pub fn result_buffer(mut self, capacity: usize) -> ThreadPoolBuilder {
+        self.result_buffer = Some(capacity);
+        self
+    }
+
+    /// * `catch` - Whether workers contain panicking jobs with `catch_unwind`. Defaults to `true`.
+    ///
+    /// When disabled, a panicking job unwinds its worker's thread and a replacement worker is
+    /// spawned with the same id. Under `panic = "abort"` neither strategy applies: a panicking
+    /// job aborts the whole process.
+    pub fn catch_panics(mut self, catch: bool) -> ThreadPoolBuilder {
+        self.catch_panics = catch;
+        self
+    }
+
+    /// * `count` - Number of workers that only run jobs submitted with
+    ///   [`ThreadPool::execute_fast`]. The remaining workers run both lanes, fast jobs first.
+    ///
+    /// At least one worker is always left for the bulk lane, so `count` is clamped to one less
+    /// than the thread count. Ignored under [`DequeueStrategy::PerWorkerChannel`], where every
+    /// worker takes whatever is dealt to it, fast or not.
+    pub fn reserved_fast_workers(mut self, count: usize) -> ThreadPoolBuilder {
+        self.reserved_fast_workers = count;
+        self
+    }
+
+    /// * `init` - Hook run on each worker thread, with the worker's id, before it takes any job.
+    ///
+    /// It runs again on the fresh thread whenever a worker is respawned or recycled, which makes
+    /// it the place to set up thread-local state. A worker whose `init` panics dies for good
+    /// rather than being respawned into the same panic.
+    pub fn worker_init<F>(mut self, init: F) -> ThreadPoolBuilder
+    where
+        F: Fn(usize) + Send + Sync + 'static,
+    {
+        self.worker_init = Some(Arc::new(init));
+        self
+    }
+
+    /// * `respawn` - Whether a worker whose thread dies from an escaped panic is replaced.
+    ///   Defaults to `true`.
+    pub fn respawn_workers(mut self, respawn: bool) -> ThreadPoolBuilder {
+        self.respawn_workers = respawn;
+        self
+    }
+
+    /// * `reject` - Whether [`ThreadPool::execute`] and friends return
+    ///   [`PoolError::NoWorkers`](crate::PoolError::NoWorkers) once every worker has died,
+    ///   instead of queueing jobs nobody will run. Defaults to `false`.
+    pub fn reject_if_no_workers(mut self, reject: bool) -> ThreadPoolBuilder {
+        self.reject_if_no_workers = reject;
+        self
+    }
+
+    /// * `prewarm` - Whether [`build`](ThreadPoolBuilder::build) waits until every worker has run
+    ///   its [`worker_init`](ThreadPoolBuilder::worker_init) hook and is waiting for jobs.
+    ///   Defaults to `false`.
+    ///
+    /// This keeps thread start-up out of the latency of the first jobs.
+    pub fn prewarm(mut self, prewarm: bool) -> ThreadPoolBuilder {
+        self.prewarm = prewarm;
+        self
+    }
+
+    /// * `handler` - Called with the error of every job submitted with
+    ///   [`ThreadPool::execute_fallible`] that returns [`Err`]. The error is boxed as
+    ///   [`Any`] so one handler can serve jobs with different error types; downcast it to
+    ///   recover the concrete type.
+    ///
+    /// Errors are dropped if no handler is registered. Panics are not routed here.
+    pub fn error_handler<H>(mut self, handler: H) -> ThreadPoolBuilder
+    where
+        H: Fn(Box<dyn Any + Send>) + Send + Sync + 'static,
+    {
+        self.error_handler = Some(Arc::new(handler));
+        self
+    }
+
+    /// * `observer` - Called on the worker with a [`ResultMeta`] after every job finishes,
+    ///   however it was submitted and whether or not it panicked.
+    pub fn result_observer<O>(mut self, observer: O) -> ThreadPoolBuilder
+    where
+        O: Fn(&ResultMeta) + Send + Sync + 'static,
+    {
+        self.result_observer = Some(Arc::new(observer));
+        self
+    }
+
+    /// * `mode` - What happens to queued jobs when the pool is shut down or dropped. Defaults to
+    ///   [`DrainMode::All`].
+    pub fn drain_mode(mut self, mode: DrainMode) -> ThreadPoolBuilder {
+        self.drain_mode = mode;
+        self
+    }
+
+    /// * `handler` - Called with every [`PoolEvent`] the pool emits, on whichever thread the
+    ///   event happened.
+    pub fn on_event<H>(mut self, handler: H) -> ThreadPoolBuilder
+    where
+        H: Fn(&PoolEvent) + Send + Sync + 'static,
+    {
+        self.event_handler = Some(Arc::new(handler));
+        self
+    }
+
+    /// * `priority` - OS scheduling priority applied to each worker thread as it starts.
+    ///   Defaults to [`ThreadPriority::Normal`].
+    ///
+    /// Supported on Linux and Windows. If the OS refuses, for instance because raising the priority
+    /// needs privileges, a [`PoolEvent::ThreadPriorityFailed`] is emitted and the worker carries on
+    /// at the default priority.
+    pub fn thread_priority(mut self, priority: ThreadPriority) -> ThreadPoolBuilder {
+        self.thread_priority = priority;
+        self
+    }
+
+    /// * `timeout` - How long any job may run before its [`CancelToken`](crate::CancelToken) is cancelled and a
+    ///   [`PoolEvent::JobTimedOut`] is emitted.
+    ///
+    /// Cancellation is cooperative: jobs fetch their token with
+    /// [`CancelToken::current`](crate::CancelToken::current) and return early once it's cancelled.
+    /// A job that never checks it runs to completion regardless, still holding its worker.
+    pub fn default_job_timeout(mut self, timeout: Duration) -> ThreadPoolBuilder {
+        self.default_job_timeout = Some(timeout);
+        self
+    }
+
+    /// * `capacity` - Most jobs that may wait in the queue at once. Jobs submitted beyond that are
+    ///   rejected with [`PoolError::QueueFull`](crate::PoolError::QueueFull). Unbounded by default.
+    pub fn queue_capacity(mut self, capacity: usize) -> ThreadPoolBuilder {
+        self.queue_capacity = Some(capacity);
+        self
+    }
+
+    /// * `verbose` - Whether workers print a line to stdout for every job they take and whenever
+    ///   they start, stop or respawn. Off by default, since printing per job costs more than a
+    ///   small job itself.
+    pub fn verbose(mut self, verbose: bool) -> ThreadPoolBuilder {
+        self.verbose = verbose;
+        self
+    }
+
+    /// * `max` - Largest closure, in bytes, the pool accepts. Bigger ones are rejected with
+    ///   [`PoolError::JobTooLarge`](crate::PoolError::JobTooLarge).
+    ///
+    /// A debugging aid for closures that accidentally capture large values, such as a big array,
+    /// by value. Put such data behind an [`Arc`] instead.
+    pub fn max_job_size(mut self, max: usize) -> ThreadPoolBuilder {
+        self.max_job_size = Some(max);
+        self
+    }
+
+    /// * `max` - Most bytes of closures that may wait in the queue at once, summed over every
+    ///   queued job. A job that would take the total past it is rejected with
+    ///   [`PoolError::QueueFull`](crate::PoolError::QueueFull). Unbounded by default.
+    ///
+    /// Bounds the memory held by a backed-up queue better than
+    /// [`queue_capacity`](ThreadPoolBuilder::queue_capacity) when job sizes vary. Only the
+    /// closures themselves count, not what they point to on the heap. A job stops counting once
+    /// a worker takes it.
+    pub fn max_queued_bytes(mut self, max: usize) -> ThreadPoolBuilder {
+        self.max_queued_bytes = Some(max);
+        self
+    }
+
+    /// * `backend` - Stores the queued jobs and decides which one runs next. Defaults to a
+    ///   [`FifoQueue`](crate::FifoQueue).
+    pub fn queue_backend<B>(mut self, backend: B) -> ThreadPoolBuilder
+    where
+        B: QueueBackend + 'static,
+    {
+        self.queue_backend = Some(Box::new(backend));
+        self
+    }
+
+    /// * `strategy` - How workers take jobs. Defaults to [`DequeueStrategy::SharedQueue`].
+    pub fn dequeue_strategy(mut self, strategy: DequeueStrategy) -> ThreadPoolBuilder {
+        self.dequeue_strategy = strategy;
+        self
+    }
+
+    /// * `deterministic` - Whether the pool runs without worker threads, queueing jobs until
+    ///   [`ThreadPool::run_pending`](crate::ThreadPool::run_pending) runs them on the calling
+    ///   thread. Off by default.
+    ///
+    /// Meant for tests of code built on the pool: jobs run one at a time in exactly the order the
+    /// queue hands them out, fast lane first, so the outcome doesn't depend on thread scheduling.
+    /// The thread count and [`dequeue_strategy`](ThreadPoolBuilder::dequeue_strategy) are
+    /// ignored, and jobs still pending when the pool is dropped never run.
+    pub fn deterministic(mut self, deterministic: bool) -> ThreadPoolBuilder {
+        self.deterministic = deterministic;
+        self
+    }
+
+    /// * `recycle` - Whether small job closures are stored in allocations the pool reuses once
+    ///   the job has run, instead of a fresh [`Box`] per job. Off by default.
+    ///
+    /// Cuts the allocator traffic of submitting many tiny jobs, where allocating the box is a
+    /// large part of the cost. Closures bigger than 64 bytes are boxed as usual.
+    pub fn recycle_job_boxes(mut self, recycle: bool) -> ThreadPoolBuilder {
+        self.recycle_job_boxes = recycle;
+        self
+    }
+
+    /// Creates the configured ThreadPool.
+    ///
+    /// # Returns
+    ///
+    /// 'Result' type that represents either success ([`Ok(ThreadPool)`]) or failure ([`Err(PoolCreationError)`])
+    pub fn build(self) -> Result<ThreadPool, PoolCreationError> {
+        if self.thread_count == 0 {
+            return Err(PoolCreationError::LessThanOne);
+        }
+
+        Ok(ThreadPool::from_builder(self))
+    }
+}
+
+impl Default for ThreadPoolBuilder {
+    fn default() -> ThreadPoolBuilder {
+        ThreadPoolBuilder::new()
+    }
+}
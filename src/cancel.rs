@@ -0,0 +1,61 @@
+use std::{
+    cell::RefCell,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+thread_local! {
+    static CURRENT: RefCell<Option<CancelToken>> = const { RefCell::new(None) };
+}
+
+/// Cooperative cancellation flag for a running job.
+///
+/// Cancelling a token doesn't stop anything by itself; a job has to check
+/// [`is_cancelled`](CancelToken::is_cancelled) and return early.
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancelToken {
+    /// Creates a token that isn't cancelled yet.
+    pub fn new() -> CancelToken {
+        CancelToken::default()
+    }
+
+    /// Flags the token. Every clone sees the change.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Whether [`cancel`](CancelToken::cancel) was called on this token or one of its clones.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// # Returns
+    ///
+    /// The token of the job running on the calling thread, or [`None`] outside a job or when the
+    /// pool has no [`default_job_timeout`](crate::ThreadPoolBuilder::default_job_timeout).
+    pub fn current() -> Option<CancelToken> {
+        CURRENT.with(|current| current.borrow().clone())
+    }
+}
+
+/// Runs `f` with `token` installed as the calling thread's [`CancelToken::current`].
+pub(crate) fn run_with<R>(token: CancelToken, f: impl FnOnce() -> R) -> R {
+    /// Puts back the previous token even if `f` unwinds.
+    struct Restore(Option<CancelToken>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let previous: Option<CancelToken> = self.0.take();
+            CURRENT.with(|current| *current.borrow_mut() = previous);
+        }
+    }
+
+    let _restore: Restore = Restore(CURRENT.with(|current| current.replace(Some(token))));
+    f()
+}
@@ -0,0 +1,171 @@
+use std::{
+    cell::RefCell,
+    sync::{atomic::Ordering, Arc, Weak},
+};
+
+use crate::{
+    handle::{self, JobHandle},
+    queue::Lane,
+    PoolError, Shared, ThreadPool,
+};
+
+thread_local! {
+    /// Pools installed with [`with_pool`] on this thread, innermost last.
+    static CURRENT: RefCell<Vec<Weak<Shared>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Handle to the pool installed with [`with_pool`], returned by [`current_pool`].
+///
+/// Doesn't keep the pool alive or hold up its shutdown: jobs submitted once the pool has started
+/// shutting down are rejected.
+#[derive(Clone)]
+pub struct PoolRef {
+    shared: Weak<Shared>,
+}
+
+/// Removes the innermost installed pool when dropped.
+struct Uninstall;
+
+impl Drop for Uninstall {
+    fn drop(&mut self) {
+        CURRENT.with(|current| current.borrow_mut().pop());
+    }
+}
+
+/// Runs `f` with `pool` as the [`current_pool`] of the calling thread, so code called from `f`
+/// can submit to it without being handed the pool.
+///
+/// * `pool` - Pool to install. Calls nest: the innermost pool is current until its `with_pool`
+///   returns, then the previous one is again.
+///
+/// * `f` - Closure to run on the calling thread.
+///
+/// # Returns
+///
+/// What `f` returns.
+pub fn with_pool<R>(pool: &ThreadPool, f: impl FnOnce() -> R) -> R {
+    CURRENT.with(|current| current.borrow_mut().push(Arc::downgrade(&pool.shared)));
+    // Uninstalled even if `f` panics.
+    let _uninstall = Uninstall;
+
+    f()
+}
+
+/// # Returns
+///
+/// [`PoolRef`] to the pool installed on the calling thread by the innermost [`with_pool`] call,
+/// or [`None`] outside of one. Jobs running on the pool's workers don't see it.
+pub fn current_pool() -> Option<PoolRef> {
+    CURRENT
+        .with(|current| current.borrow().last().cloned())
+        .map(|shared| PoolRef { shared })
+}
+
+impl PoolRef {
+    /// Runs a job on the referenced pool, like [`ThreadPool::execute`].
+    ///
+    /// * `f` - Closure to run on one of the pool's workers.
+    ///
+    /// # Returns
+    ///
+    /// [`Err(PoolError::ShuttingDown)`](PoolError::ShuttingDown) if the pool is shutting down or
+    /// gone, or any error [`ThreadPool::execute`] returns.
+    pub fn execute<F>(&self, f: F) -> Result<(), PoolError>
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        let shared: Arc<Shared> = self.live_pool().ok_or(PoolError::ShuttingDown)?;
+        let job = shared.job(f);
+
+        shared.enqueue(job, Lane::Back)
+    }
+
+    /// Submits a job whose return value can be collected later, like [`ThreadPool::submit`].
+    ///
+    /// * `f` - Closure to run on one of the pool's workers.
+    ///
+    /// # Returns
+    ///
+    /// [`JobHandle<T>`] for the closure's return value. It yields
+    /// [`JoinError::Cancelled`](crate::JoinError::Cancelled) if the pool is shutting down or gone.
+    pub fn submit<F, T>(&self, f: F) -> JobHandle<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let (promise, handle) = handle::promise(self.shared.clone());
+        if let Some(shared) = self.live_pool() {
+            let _ = handle::submit_into(&shared, promise, f);
+        }
+
+        handle
+    }
+
+    /// Whether the pool has started shutting down, or is gone.
+    pub fn is_shutting_down(&self) -> bool {
+        self.live_pool().is_none()
+    }
+
+    fn live_pool(&self) -> Option<Arc<Shared>> {
+        self.shared
+            .upgrade()
+            .filter(|shared| !shared.stopping.load(Ordering::SeqCst))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{atomic::Ordering, Arc};
+
+    use crate::{current_pool, join_all, with_pool, PoolError, Shared, ThreadPool};
+
+    /// Library code that parallelizes on whatever pool its caller installed.
+    fn squares(values: Vec<usize>) -> Vec<usize> {
+        let pool = current_pool().expect("no pool installed");
+
+        join_all(
+            values
+                .into_iter()
+                .map(|value| pool.submit(move || value * value))
+                .collect(),
+        )
+        .into_iter()
+        .map(|result| result.unwrap())
+        .collect()
+    }
+
+    #[test]
+    fn test_nested_code_submits_to_ambient_pool() {
+        let outer: ThreadPool = ThreadPool::new(2);
+        let inner: ThreadPool = ThreadPool::new(2);
+        assert!(current_pool().is_none());
+
+        let (from_outer, from_inner) = with_pool(&outer, || {
+            let from_inner: Vec<usize> = with_pool(&inner, || squares(vec![4, 5]));
+            (squares(vec![1, 2, 3]), from_inner)
+        });
+
+        assert_eq!(from_outer, vec![1, 4, 9]);
+        assert_eq!(from_inner, vec![16, 25]);
+        assert_eq!(outer.metrics().jobs_submitted, 3);
+        assert_eq!(inner.metrics().jobs_submitted, 2);
+        assert!(current_pool().is_none());
+    }
+
+    #[test]
+    fn test_shutdown_stops_workers_while_handles_exist() {
+        let threadpool: ThreadPool = ThreadPool::new(3);
+        let handle = with_pool(&threadpool, || current_pool().unwrap());
+        let clone = handle.clone();
+        // Outlives the pool the way any leftover reference to its state could.
+        let shared: Arc<Shared> = Arc::clone(&threadpool.shared);
+        assert_eq!(handle.submit(|| 1).join().unwrap(), 1);
+
+        threadpool.shutdown();
+
+        assert_eq!(shared.alive_workers.load(Ordering::SeqCst), 0);
+        assert!(clone.is_shutting_down());
+        assert_eq!(clone.execute(|| ()), Err(PoolError::ShuttingDown));
+        assert!(handle.submit(|| 2).join().is_err());
+    }
+}
@@ -0,0 +1,371 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    mpsc,
+};
+
+use crate::job::Job;
+
+/// How workers take jobs, set with
+/// [`ThreadPoolBuilder::dequeue_strategy`](crate::ThreadPoolBuilder::dequeue_strategy).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DequeueStrategy {
+    /// Every worker takes jobs from one queue behind a mutex. Supports every scheduling option.
+    #[default]
+    SharedQueue,
+    /// Each worker owns the receiving end of its own channel, and jobs are dealt out to the
+    /// workers in turn. Workers don't contend on the queue's mutex to take a job, but a job only
+    /// ever runs on the worker it was dealt to, even if another worker is free.
+    ///
+    /// Job pickup still isn't free of locks. Every job briefly takes the read side of the lock
+    /// behind [`ThreadPool::snapshot_consistent`](crate::ThreadPool::snapshot_consistent) as
+    /// it's submitted, started and finished, and the mutex behind
+    /// [`ThreadPool::running_jobs`](crate::ThreadPool::running_jobs) as it starts and finishes.
+    /// A worker that finds its channel empty takes the queue's mutex to count itself idle for
+    /// [`ThreadPool::wait_for_idle_workers`](crate::ThreadPool::wait_for_idle_workers). Submitting
+    /// read-locks the set of channels, and also serializes on a mutex under a
+    /// [`queue_capacity`](crate::ThreadPoolBuilder::queue_capacity).
+    ///
+    /// The fast lane, [`ThreadPool::execute_front`](crate::ThreadPool::execute_front),
+    /// [`queue_backend`](crate::ThreadPoolBuilder::queue_backend) and
+    /// [`reserved_fast_workers`](crate::ThreadPoolBuilder::reserved_fast_workers) don't apply,
+    /// and [`ThreadPool::queued_job_names`](crate::ThreadPool::queued_job_names) can't see into
+    /// the channels.
+    PerWorkerChannel,
+}
+
+/// What a worker receives on its channel.
+pub(crate) enum Message {
+    Job(Job),
+    /// Makes the worker exit once it has handled every message sent before this one.
+    Stop,
+}
+
+/// Receiving end of a worker's channel, owned by the worker's thread.
+pub(crate) type Inbox = mpsc::Receiver<Message>;
+
+/// Empties and drops the `inbox` of a worker that is gone, so
+/// [`Channels::send`] skips it from then on.
+///
+/// # Returns
+///
+/// The jobs that were left in it.
+pub(crate) fn close_inbox(inbox: Inbox) -> Vec<Job> {
+    inbox
+        .try_iter()
+        .filter_map(|message| match message {
+            Message::Job(job) => Some(job),
+            Message::Stop => None,
+        })
+        .collect()
+}
+
+/// Sending ends of the per-worker channels, indexed by worker id.
+pub(crate) struct Channels {
+    senders: Vec<mpsc::Sender<Message>>,
+    /// Set for the workers whose inbox is gone for good. Only changed under the pool's `channels`
+    /// write lock, so whoever deals jobs under its read lock sees a stable set of inboxes.
+    closed: Vec<bool>,
+    /// Worker the next job is dealt to.
+    next: AtomicUsize,
+}
+
+impl Channels {
+    /// Creates a channel for each of `count` workers.
+    ///
+    /// # Returns
+    ///
+    /// The sending ends, and the receiving ends in worker id order.
+    pub(crate) fn new(count: usize) -> (Channels, Vec<Inbox>) {
+        let (senders, inboxes) = (0..count).map(|_| mpsc::channel()).unzip();
+        let channels: Channels = Channels {
+            senders,
+            closed: vec![false; count],
+            next: AtomicUsize::new(0),
+        };
+
+        (channels, inboxes)
+    }
+
+    /// Deals `job` to the next worker, or to the worker its affinity maps to, skipping workers
+    /// whose thread died for good.
+    ///
+    /// # Returns
+    ///
+    /// [`Err`] with the job if no worker is left to take it.
+    pub(crate) fn send(&self, job: Job) -> Result<(), Job> {
+        let start: usize = match job.affinity {
+            Some(affinity) => (affinity % self.senders.len() as u64) as usize,
+            None => self.next.fetch_add(1, Ordering::Relaxed),
+        };
+        let mut message: Message = Message::Job(job);
+
+        for offset in 0..self.senders.len() {
+            let worker_id: usize = (start + offset) % self.senders.len();
+            if self.closed[worker_id] {
+                continue;
+            }
+
+            match self.senders[worker_id].send(message) {
+                Ok(()) => return Ok(()),
+                Err(mpsc::SendError(returned)) => message = returned,
+            }
+        }
+
+        match message {
+            Message::Job(job) => Err(job),
+            Message::Stop => unreachable!("only jobs are dealt out"),
+        }
+    }
+
+    /// Marks the inbox of worker `worker_id` as gone, just before it's dropped, so no more jobs
+    /// are dealt to it.
+    pub(crate) fn close(&mut self, worker_id: usize) {
+        if let Some(closed) = self.closed.get_mut(worker_id) {
+            *closed = true;
+        }
+    }
+
+    /// Whether any worker's inbox is left. If so, [`Channels::send`] succeeds for as long as the
+    /// pool's `channels` lock is held.
+    pub(crate) fn is_open(&self) -> bool {
+        self.closed.iter().any(|&closed| !closed)
+    }
+
+    /// Asks worker `worker_id` to exit once it has handled the messages already sent to it.
+    pub(crate) fn stop(&self, worker_id: usize) {
+        if let Some(sender) = self.senders.get(worker_id) {
+            let _ = sender.send(Message::Stop);
+        }
+    }
+
+    /// Asks every worker to exit once it has handled the messages already sent to it.
+    pub(crate) fn stop_all(&self) {
+        for worker_id in 0..self.senders.len() {
+            self.stop(worker_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        collections::HashMap,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            mpsc, Arc, Mutex,
+        },
+        thread::{self, ThreadId},
+        time::Duration,
+    };
+
+    use crate::{DequeueStrategy, ThreadPool};
+
+    #[test]
+    fn test_per_worker_channels_run_every_job() {
+        let threadpool: ThreadPool = ThreadPool::builder()
+            .num_threads(4)
+            .dequeue_strategy(DequeueStrategy::PerWorkerChannel)
+            .build()
+            .unwrap();
+        let total: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+
+        // Each worker's thread owns its receiver outright, and the shared queue is never used.
+        assert_eq!(
+            threadpool
+                .shared
+                .channels
+                .read()
+                .unwrap()
+                .as_ref()
+                .unwrap()
+                .senders
+                .len(),
+            4
+        );
+        for i in 0..1_000 {
+            let total = Arc::clone(&total);
+            threadpool
+                .execute_named(format!("job {i}"), move || {
+                    total.fetch_add(i, Ordering::SeqCst);
+                })
+                .unwrap();
+        }
+        assert!(threadpool.queued_job_names().is_empty());
+
+        threadpool.wait_for_idle_workers(4);
+        assert_eq!(total.load(Ordering::SeqCst), (0..1_000).sum::<usize>());
+
+        threadpool.recycle_worker(2);
+        assert_eq!(threadpool.submit(|| 7).join().unwrap(), 7);
+    }
+
+    #[test]
+    fn test_same_affinity_runs_on_one_worker() {
+        let threadpool: ThreadPool = ThreadPool::builder()
+            .num_threads(4)
+            .dequeue_strategy(DequeueStrategy::PerWorkerChannel)
+            .build()
+            .unwrap();
+        let runs: Arc<Mutex<HashMap<(u64, ThreadId), usize>>> =
+            Arc::new(Mutex::new(HashMap::new()));
+
+        for i in 0..40 {
+            let affinity: u64 = if i % 2 == 0 { 7 } else { i % 4 };
+            let runs = Arc::clone(&runs);
+            threadpool
+                .execute_with_affinity(affinity, move || {
+                    *runs
+                        .lock()
+                        .unwrap()
+                        .entry((affinity, thread::current().id()))
+                        .or_default() += 1;
+                })
+                .unwrap();
+        }
+        threadpool.wait_for_idle_workers(4);
+
+        let runs = runs.lock().unwrap();
+        let workers = |affinity: u64| -> Vec<ThreadId> {
+            runs.keys()
+                .filter(|(key, _)| *key == affinity)
+                .map(|&(_, thread)| thread)
+                .collect()
+        };
+        assert_eq!(workers(7).len(), 1);
+        assert_eq!(runs[&(7, workers(7)[0])], 20);
+        assert_eq!(workers(1).len(), 1);
+        assert_eq!(workers(3).len(), 1);
+        assert_ne!(workers(1), workers(3));
+        // 7 % 4 == 3
+        assert_eq!(workers(7), workers(3));
+    }
+
+    #[test]
+    fn test_remove_workers_redeals_their_jobs() {
+        let mut threadpool: ThreadPool = ThreadPool::builder()
+            .num_threads(4)
+            .dequeue_strategy(DequeueStrategy::PerWorkerChannel)
+            .build()
+            .unwrap();
+        let total: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+
+        for _ in 0..100 {
+            let total = Arc::clone(&total);
+            threadpool
+                .execute(move || {
+                    thread::sleep(Duration::from_micros(200));
+                    total.fetch_add(1, Ordering::SeqCst);
+                })
+                .unwrap();
+        }
+        assert_eq!(threadpool.remove_workers(2), 2);
+        for _ in 0..20 {
+            let total = Arc::clone(&total);
+            threadpool
+                .execute(move || {
+                    total.fetch_add(1, Ordering::SeqCst);
+                })
+                .unwrap();
+        }
+
+        threadpool.wait_for_idle_workers(2);
+        assert_eq!(total.load(Ordering::SeqCst), 120);
+    }
+
+    #[test]
+    fn test_remove_workers_runs_their_backlog_first() {
+        let mut threadpool: ThreadPool = ThreadPool::builder()
+            .num_threads(2)
+            .dequeue_strategy(DequeueStrategy::PerWorkerChannel)
+            .build()
+            .unwrap();
+        let ran: Arc<Mutex<Vec<usize>>> = Arc::new(Mutex::new(Vec::new()));
+
+        // Dealt in turn, so the odd jobs go to worker 1.
+        for i in 0..20 {
+            let ran = Arc::clone(&ran);
+            threadpool
+                .execute(move || {
+                    thread::sleep(Duration::from_millis(1));
+                    ran.lock().unwrap().push(i);
+                })
+                .unwrap();
+        }
+        assert_eq!(threadpool.remove_workers(1), 1);
+
+        let ran: Vec<usize> = ran.lock().unwrap().clone();
+        assert!((1..20).step_by(2).all(|i| ran.contains(&i)));
+    }
+
+    #[test]
+    fn test_switch_to_per_worker_queues_restarts_each_worker_when_it_is_done() {
+        let (open_gate, gate) = mpsc::channel::<()>();
+        let gate: Mutex<mpsc::Receiver<()>> = Mutex::new(gate);
+        // Worker 1 is held back so worker 0 takes the long job.
+        let mut threadpool: ThreadPool = ThreadPool::builder()
+            .num_threads(2)
+            .worker_init(move |id| {
+                if id == 1 {
+                    let _ = gate.lock().unwrap().recv_timeout(Duration::from_secs(10));
+                }
+            })
+            .build()
+            .unwrap();
+        let (release, released) = mpsc::channel::<()>();
+        let (sender, outcome) = mpsc::channel::<bool>();
+
+        threadpool
+            .execute(move || {
+                let in_time: bool = released.recv_timeout(Duration::from_secs(10)).is_ok();
+                sender.send(in_time).unwrap();
+            })
+            .unwrap();
+        while threadpool.metrics().active_jobs < 1 {
+            thread::yield_now();
+        }
+        // Dealt one to each worker. Only worker 1 can run its copy while worker 0 is busy.
+        for _ in 0..2 {
+            let release = release.clone();
+            threadpool
+                .execute(move || {
+                    let _ = release.send(());
+                })
+                .unwrap();
+        }
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100));
+            open_gate.send(()).unwrap();
+        });
+        threadpool.switch_to_per_worker_queues();
+
+        assert!(
+            outcome.recv().unwrap(),
+            "worker 1 waited for worker 0's job"
+        );
+    }
+
+    #[test]
+    fn test_switch_to_per_worker_queues_keeps_queued_jobs() {
+        let mut threadpool: ThreadPool = ThreadPool::new(3);
+        let runs: Arc<Vec<AtomicUsize>> = Arc::new((0..200).map(|_| AtomicUsize::new(0)).collect());
+        let submit = |threadpool: &ThreadPool, i: usize| {
+            let runs = Arc::clone(&runs);
+            threadpool
+                .execute(move || {
+                    thread::sleep(Duration::from_micros(200));
+                    runs[i].fetch_add(1, Ordering::SeqCst);
+                })
+                .unwrap();
+        };
+
+        (0..100).for_each(|i| submit(&threadpool, i));
+        threadpool.switch_to_per_worker_queues();
+        assert!(threadpool.shared.channels.read().unwrap().is_some());
+        (100..200).for_each(|i| submit(&threadpool, i));
+
+        threadpool.wait_for_idle_workers(3);
+        for (i, count) in runs.iter().enumerate() {
+            assert_eq!(count.load(Ordering::SeqCst), 1, "job {i}");
+        }
+    }
+}
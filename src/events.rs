@@ -0,0 +1,22 @@
+use std::sync::Arc;
+
+/// Something noteworthy that happened inside a pool, passed to the
+/// [`on_event`](crate::ThreadPoolBuilder::on_event) callback.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PoolEvent {
+    /// A worker couldn't apply the configured
+    /// [`thread_priority`](crate::ThreadPoolBuilder::thread_priority) and runs at the default
+    /// priority instead.
+    ThreadPriorityFailed { worker_id: usize, error: String },
+    /// A job ran past the [`default_job_timeout`](crate::ThreadPoolBuilder::default_job_timeout)
+    /// and its [`CancelToken`](crate::CancelToken) was cancelled. The job keeps running until it
+    /// notices.
+    JobTimedOut {
+        worker_id: usize,
+        job_name: Option<String>,
+    },
+}
+
+/// Receives [`PoolEvent`]s, see [`ThreadPoolBuilder::on_event`](crate::ThreadPoolBuilder::on_event).
+pub(crate) type EventHandler = Arc<dyn Fn(&PoolEvent) + Send + Sync>;
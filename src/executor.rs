@@ -0,0 +1,85 @@
+use std::{
+    future::Future,
+    pin::pin,
+    sync::Arc,
+    task::{Context, Poll, Wake, Waker},
+    thread::{self, Thread},
+};
+
+/// Wakes the worker thread that is blocked on a future.
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<ThreadWaker>) {
+        self.0.unpark();
+    }
+
+    fn wake_by_ref(self: &Arc<ThreadWaker>) {
+        self.0.unpark();
+    }
+}
+
+/// Polls `fut` to completion on the current thread, parking between polls.
+///
+/// * `fut` - Future to drive.
+///
+/// # Returns
+///
+/// The future's output.
+pub(crate) fn block_on<F: Future>(fut: F) -> F::Output {
+    let mut fut = pin!(fut);
+    let waker: Waker = Arc::new(ThreadWaker(thread::current())).into();
+    let mut context: Context = Context::from_waker(&waker);
+
+    loop {
+        match fut.as_mut().poll(&mut context) {
+            Poll::Ready(output) => return output,
+            // Spurious unparks only cost an extra poll.
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        future::Future,
+        pin::Pin,
+        task::{Context, Poll},
+    };
+
+    use crate::ThreadPool;
+
+    /// Returns [`Poll::Pending`] once, waking itself straight away.
+    struct YieldNow(bool);
+
+    impl Future for YieldNow {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut YieldNow>, cx: &mut Context<'_>) -> Poll<()> {
+            if self.0 {
+                return Poll::Ready(());
+            }
+
+            self.0 = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+
+    #[test]
+    fn test_block_on_future_yields_value() {
+        let threadpool: ThreadPool = ThreadPool::new(2);
+
+        let handle = threadpool.block_on_future(async {
+            let mut total: usize = 0;
+            for i in 1..=3 {
+                YieldNow(false).await;
+                total += i;
+            }
+            total
+        });
+
+        assert_eq!(handle.join().unwrap(), 6);
+    }
+}
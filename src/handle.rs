@@ -0,0 +1,397 @@
+use std::{
+    any::Any,
+    error, fmt,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, Condvar, Mutex, Weak,
+    },
+};
+
+use crate::{job::Job, queue::Lane, PoolError, Shared};
+
+/// Why a [`JobHandle`] has no value to return.
+#[derive(Debug)]
+pub enum JoinError {
+    /// The job panicked. Holds the panic payload.
+    Panicked(Box<dyn Any + Send>),
+    /// The job never ran: it was cancelled with [`JobHandle::cancel`], rejected by the pool, or
+    /// discarded from the queue on shutdown.
+    Cancelled,
+}
+
+impl JoinError {
+    /// # Returns
+    ///
+    /// The panic payload, or a message payload for a cancelled job, ready for
+    /// [`panic::resume_unwind`].
+    pub fn into_panic(self) -> Box<dyn Any + Send> {
+        match self {
+            JoinError::Panicked(payload) => payload,
+            JoinError::Cancelled => Box::new("job was cancelled"),
+        }
+    }
+}
+
+impl fmt::Display for JoinError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JoinError::Panicked(_) => write!(f, "job panicked"),
+            JoinError::Cancelled => write!(f, "job was cancelled before it ran"),
+        }
+    }
+}
+
+impl error::Error for JoinError {}
+
+/// Callback run with a job's result instead of storing it.
+type Continuation<T> = Box<dyn FnOnce(Result<T, JoinError>) + Send + 'static>;
+
+/// Where a job's result meets whoever is waiting for it.
+struct Completion<T> {
+    slot: Mutex<Slot<T>>,
+    done: Condvar,
+    /// Set by [`JobHandle::cancel`] so the job is skipped if it hasn't started yet.
+    cancelled: AtomicBool,
+}
+
+struct Slot<T> {
+    result: Option<Result<T, JoinError>>,
+    continuation: Option<Continuation<T>>,
+    /// Whether a result was delivered. Later ones, such as that of a job cancelled while it
+    /// ran, are ignored.
+    completed: bool,
+}
+
+/// Handle to the result of a job submitted with [`ThreadPool::submit`](crate::ThreadPool::submit).
+pub struct JobHandle<T> {
+    completion: Arc<Completion<T>>,
+    pool: Weak<Shared>,
+}
+
+/// Write end of a [`JobHandle`]. Dropping it without completing fails the handle.
+pub(crate) struct Promise<T> {
+    completion: Option<Arc<Completion<T>>>,
+    /// Whether the job is skipped once nobody holds its handle, see
+    /// [`ThreadPool::execute_weak`](crate::ThreadPool::execute_weak).
+    skip_if_abandoned: bool,
+}
+
+/// Creates a connected [`Promise`] and [`JobHandle`].
+///
+/// * `pool` - Pool that follow-up jobs chained with [`JobHandle::then`] are submitted to.
+pub(crate) fn promise<T>(pool: Weak<Shared>) -> (Promise<T>, JobHandle<T>) {
+    let completion: Arc<Completion<T>> = Arc::new(Completion {
+        slot: Mutex::new(Slot {
+            result: None,
+            continuation: None,
+            completed: false,
+        }),
+        done: Condvar::new(),
+        cancelled: AtomicBool::new(false),
+    });
+
+    let promise: Promise<T> = Promise {
+        completion: Some(Arc::clone(&completion)),
+        skip_if_abandoned: false,
+    };
+    (promise, JobHandle { completion, pool })
+}
+
+/// Queues `f` on `shared`, completing `promise` with its result.
+pub(crate) fn submit_into<F, T>(shared: &Shared, promise: Promise<T>, f: F) -> Result<(), PoolError>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let job: Job = shared.job(move || {
+        // Dropping the promise of a cancelled job changes nothing; its handle already knows.
+        if promise.is_cancelled() || promise.is_abandoned() {
+            return;
+        }
+
+        let result: Result<T, JoinError> =
+            panic::catch_unwind(AssertUnwindSafe(f)).map_err(JoinError::Panicked);
+        let panicked: bool = result.is_err();
+        promise.complete(result);
+
+        // The payload went to the handle; re-raise so the worker still counts the panic.
+        if panicked {
+            panic::resume_unwind(Box::new("job panicked"));
+        }
+    });
+
+    // A rejected job drops `promise`, which the handle reports as an error.
+    shared.enqueue(job, Lane::Back)
+}
+
+impl<T> Promise<T> {
+    pub(crate) fn complete(mut self, result: Result<T, JoinError>) {
+        if let Some(completion) = self.completion.take() {
+            completion.complete(result);
+        }
+    }
+
+    /// Makes the job skip running if its handle is gone by the time a worker takes it.
+    pub(crate) fn skip_if_abandoned(mut self) -> Promise<T> {
+        self.skip_if_abandoned = true;
+        self
+    }
+
+    /// Whether nobody can receive the result anymore: the handle was dropped without chaining a
+    /// follow-up job onto it.
+    fn is_abandoned(&self) -> bool {
+        let Some(completion) = self.completion.as_ref().filter(|_| self.skip_if_abandoned) else {
+            return false;
+        };
+
+        Arc::strong_count(completion) == 1 && completion.slot.lock().unwrap().continuation.is_none()
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.completion
+            .as_ref()
+            .is_some_and(|completion| completion.cancelled.load(Ordering::SeqCst))
+    }
+}
+
+impl<T> Drop for Promise<T> {
+    fn drop(&mut self) {
+        if let Some(completion) = self.completion.take() {
+            completion.complete(Err(JoinError::Cancelled));
+        }
+    }
+}
+
+impl<T> Completion<T> {
+    fn complete(&self, result: Result<T, JoinError>) {
+        let mut slot = self.slot.lock().unwrap();
+        if slot.completed {
+            return;
+        }
+        slot.completed = true;
+
+        match slot.continuation.take() {
+            Some(continuation) => {
+                drop(slot);
+                continuation(result);
+            }
+            None => {
+                slot.result = Some(result);
+                drop(slot);
+                self.done.notify_all();
+            }
+        }
+    }
+}
+
+impl<T> JobHandle<T> {
+    /// Blocks until the job has finished, or returns straight away if it was cancelled.
+    ///
+    /// # Returns
+    ///
+    /// The job's return value, [`JoinError::Panicked`] with the panic payload if the job
+    /// panicked, or [`JoinError::Cancelled`] if it never ran.
+    pub fn join(self) -> Result<T, JoinError> {
+        let slot = self.completion.slot.lock().unwrap();
+        let mut slot = self
+            .completion
+            .done
+            .wait_while(slot, |slot| slot.result.is_none())
+            .unwrap();
+
+        slot.result.take().unwrap()
+    }
+
+    /// Cancels the job if it hasn't started yet. It stays queued but is skipped once a worker
+    /// takes it, and [`JobHandle::join`] returns [`JoinError::Cancelled`] right away.
+    ///
+    /// A job that is already running isn't interrupted, and its result is discarded.
+    pub fn cancel(&self) {
+        self.completion.cancelled.store(true, Ordering::SeqCst);
+        self.completion.complete(Err(JoinError::Cancelled));
+    }
+
+    /// Runs `continuation` with the job's result as soon as it's available, on whichever thread
+    /// completes the job, or straight away if it already has.
+    fn on_complete(self, continuation: Continuation<T>) {
+        let mut slot = self.completion.slot.lock().unwrap();
+
+        match slot.result.take() {
+            Some(result) => {
+                drop(slot);
+                continuation(result);
+            }
+            None => slot.continuation = Some(continuation),
+        }
+    }
+}
+
+impl<T: Send + 'static> JobHandle<T> {
+    /// Chains a follow-up job that receives this job's return value.
+    ///
+    /// * `g` - Closure submitted to the same pool once this job has returned.
+    ///
+    /// # Returns
+    ///
+    /// [`JobHandle<U>`] for `g`'s return value. If this job panicked or was cancelled, `g`
+    /// never runs and the returned handle yields the same error.
+    pub fn then<U, G>(self, g: G) -> JobHandle<U>
+    where
+        G: FnOnce(T) -> U + Send + 'static,
+        U: Send + 'static,
+    {
+        let pool: Weak<Shared> = self.pool.clone();
+        let (promise, handle) = promise(pool.clone());
+
+        self.on_complete(Box::new(move |result| match result {
+            Ok(value) => {
+                // A pool that has shut down drops `promise`, failing the handle.
+                if let Some(shared) = pool.upgrade() {
+                    let _ = submit_into(&shared, promise, move || g(value));
+                }
+            }
+            Err(err) => promise.complete(Err(err)),
+        }));
+
+        handle
+    }
+}
+
+/// Joins every handle in `handles`.
+///
+/// # Returns
+///
+/// The results in the same order as `handles`. Each is a [`JoinError`] rather than the panic
+/// payload of a [`thread::Result`](std::thread::Result), so a job that never ran can be told
+/// apart from one that panicked; [`JoinError::into_panic`] recovers the payload.
+pub fn join_all<T>(handles: Vec<JobHandle<T>>) -> Vec<Result<T, JoinError>> {
+    handles.into_iter().map(JobHandle::join).collect()
+}
+
+/// Waits for whichever handle in `handles` finishes first.
+///
+/// The other jobs keep running; their results are discarded.
+///
+/// # Returns
+///
+/// The index of the first handle to finish, along with its result. As with [`join_all`], a
+/// failed job yields a [`JoinError`] rather than a bare panic payload, so a cancelled job isn't
+/// mistaken for a panicked one.
+///
+/// # Panics
+///
+/// This function will panic if `handles` is empty.
+pub fn select<T: Send + 'static>(handles: Vec<JobHandle<T>>) -> (usize, Result<T, JoinError>) {
+    let (sender, finished) = mpsc::channel();
+
+    for (index, handle) in handles.into_iter().enumerate() {
+        let sender: mpsc::Sender<(usize, Result<T, JoinError>)> = sender.clone();
+        handle.on_complete(Box::new(move |result| {
+            let _ = sender.send((index, result));
+        }));
+    }
+    drop(sender);
+
+    finished.recv().expect("select needs at least one handle")
+}
+
+#[cfg(test)]
+mod test {
+    use std::{thread, time::Duration};
+
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc,
+    };
+
+    use crate::{join_all, select, JoinError, ThreadPool};
+
+    #[test]
+    fn test_join_all_preserves_order() {
+        let pool: ThreadPool = ThreadPool::new(4);
+
+        let handles = (0..10).map(|i| pool.submit(move || i * i)).collect();
+        let results: Vec<usize> = join_all(handles)
+            .into_iter()
+            .map(|result| result.unwrap())
+            .collect();
+
+        assert_eq!(results, (0..10).map(|i| i * i).collect::<Vec<usize>>());
+    }
+
+    #[test]
+    fn test_then_chains_follow_up_job() {
+        let pool: ThreadPool = ThreadPool::new(2);
+
+        let handle = pool.submit(|| 2).then(|x| x + 3);
+
+        assert_eq!(handle.join().unwrap(), 5);
+    }
+
+    #[test]
+    fn test_then_skips_follow_up_after_panic() {
+        let pool: ThreadPool = ThreadPool::new(2);
+
+        let handle = pool
+            .submit(|| -> usize { panic!("first job") })
+            .then(|x| x + 3);
+
+        assert!(handle.join().is_err());
+    }
+
+    #[test]
+    fn test_join_returns_promptly_for_cancelled_job() {
+        let pool: ThreadPool = ThreadPool::new(1);
+        let (release, gate) = mpsc::channel::<()>();
+        let blocker = pool.submit(move || gate.recv().unwrap());
+
+        let queued = pool.submit(|| 5);
+        queued.cancel();
+
+        assert!(matches!(queued.join(), Err(JoinError::Cancelled)));
+        release.send(()).unwrap();
+        blocker.join().unwrap();
+        assert_eq!(pool.submit(|| 6).join().unwrap(), 6);
+    }
+
+    #[test]
+    fn test_weak_job_is_skipped_once_its_handle_is_dropped() {
+        let pool: ThreadPool = ThreadPool::new(1);
+        let (release, gate) = mpsc::channel::<()>();
+        pool.execute(move || gate.recv().unwrap()).unwrap();
+        let ran: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
+
+        let abandoned = {
+            let ran = Arc::clone(&ran);
+            pool.execute_weak(move || ran.store(true, Ordering::SeqCst))
+        };
+        let kept = pool.execute_weak(|| 3);
+        let chained = pool.execute_weak(|| 4).then(|x| x * 10);
+        drop(abandoned);
+        release.send(()).unwrap();
+
+        assert_eq!(kept.join().unwrap(), 3);
+        assert_eq!(chained.join().unwrap(), 40);
+        pool.wait_for_idle_workers(1);
+        assert!(!ran.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_select_returns_first_to_finish() {
+        let pool: ThreadPool = ThreadPool::new(2);
+
+        let slow = pool.submit(|| {
+            thread::sleep(Duration::from_millis(200));
+            "slow"
+        });
+        let fast = pool.submit(|| {
+            thread::sleep(Duration::from_millis(10));
+            "fast"
+        });
+        let (index, result) = select(vec![slow, fast]);
+
+        assert_eq!(index, 1);
+        assert_eq!(result.unwrap(), "fast");
+    }
+}
@@ -0,0 +1,74 @@
+use std::{mem, time::Instant};
+
+use crate::{recycle::RecycledTask, worker::Runnable};
+
+/// Closure that makes up the work of a [`Job`].
+pub(crate) enum Task {
+    Boxed(Box<dyn FnOnce() + Send + 'static>),
+    /// Stored in an allocation that goes back to the pool's
+    /// [`BoxPool`](crate::recycle::BoxPool) once the job has run.
+    Recycled(RecycledTask),
+}
+
+impl Runnable for Task {
+    fn run(self) {
+        match self {
+            Task::Boxed(task) => task(),
+            Task::Recycled(task) => task.run(),
+        }
+    }
+}
+
+/// A queued unit of work together with the metadata the pool keeps about it.
+///
+/// Opaque outside the crate: a [`QueueBackend`](crate::QueueBackend) can only hold jobs, reorder
+/// them and inspect their [`name`](Job::name).
+pub struct Job {
+    pub(crate) name: Option<String>,
+    pub(crate) task: Task,
+    /// Size of the closure before it was boxed.
+    pub(crate) size: usize,
+    /// Key of the worker the job should preferably run on, see
+    /// [`ThreadPool::execute_with_affinity`](crate::ThreadPool::execute_with_affinity).
+    pub(crate) affinity: Option<u64>,
+    /// When the job was handed to the pool.
+    pub(crate) queued_at: Instant,
+}
+
+impl Job {
+    pub(crate) fn new<F>(f: F) -> Job
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        Job::from_task(Task::Boxed(Box::new(f)), mem::size_of::<F>())
+    }
+
+    /// * `size` - Size of the closure in `task`.
+    pub(crate) fn from_task(task: Task, size: usize) -> Job {
+        Job {
+            name: None,
+            task,
+            size,
+            affinity: None,
+            queued_at: Instant::now(),
+        }
+    }
+
+    pub(crate) fn named(mut self, name: impl Into<String>) -> Job {
+        self.name = Some(name.into());
+        self
+    }
+
+    pub(crate) fn with_affinity(mut self, affinity: u64) -> Job {
+        self.affinity = Some(affinity);
+        self
+    }
+
+    /// # Returns
+    ///
+    /// The name given with [`ThreadPool::execute_named`](crate::ThreadPool::execute_named), if
+    /// any.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+}
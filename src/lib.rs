@@ -1,149 +1,2438 @@
 use std::{
-    sync::{mpsc, Arc, Mutex},
+    any::Any,
+    collections::HashMap,
+    error, fmt,
+    future::Future,
+    mem,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{self, AtomicBool, AtomicUsize, Ordering},
+        mpsc, Arc, Condvar, Mutex, MutexGuard, RwLock,
+    },
     thread,
-    thread::JoinHandle,
+    time::{Duration, Instant},
 };
 
+mod builder;
+mod cancel;
+mod current;
+mod dispatch;
+mod events;
+mod executor;
+mod handle;
+mod job;
+mod metrics;
+mod ordering;
+mod os_thread;
+mod par_iter;
+mod pipeline;
+mod priority;
+mod producer;
+mod queue;
+mod recycle;
+mod results;
+mod sizing;
+mod tracking;
+mod typed;
+mod watchdog;
+mod worker;
+
+pub use builder::ThreadPoolBuilder;
+pub use cancel::CancelToken;
+pub use current::{current_pool, with_pool, PoolRef};
+pub use dispatch::DequeueStrategy;
+pub use events::PoolEvent;
+pub use handle::{join_all, select, JobHandle, JoinError};
+pub use job::Job;
+pub use metrics::{Metrics, PoolSnapshot};
+pub use pipeline::Pipeline;
+pub use priority::ThreadPriority;
+pub use producer::ProducerHandle;
+pub use queue::{DrainMode, FifoQueue, QueueBackend};
+pub use results::ResultSender;
+pub use sizing::{suggested_threads, Workload};
+pub use tracking::{JobId, JobTiming};
+pub use typed::TypedThreadPool;
+
+use dispatch::{Channels, Inbox};
+use events::EventHandler;
+use job::Task;
+use metrics::Counters;
+use ordering::{StartOrder, Ticket};
+use os_thread::WorkerThread;
+use queue::{JobQueue, Lane};
+use recycle::BoxPool;
+use tracking::Tracker;
+use watchdog::Watchdog;
+use worker::Worker;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum PoolCreationError {
     LessThanOne, // Thread count provided equals 0 or less
 }
 
+impl fmt::Display for PoolCreationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PoolCreationError::LessThanOne => {
+                write!(f, "thread pool must have at least one thread")
+            }
+        }
+    }
+}
+
+impl error::Error for PoolCreationError {}
+
+/// Reasons a job can't be handed to a [`ThreadPool`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PoolError {
+    /// Every worker thread has died and none were respawned.
+    NoWorkers,
+    /// The queue already holds as many jobs as the
+    /// [`queue_capacity`](ThreadPoolBuilder::queue_capacity) allows, or too many bytes of
+    /// closures for the [`max_queued_bytes`](ThreadPoolBuilder::max_queued_bytes) budget.
+    QueueFull,
+    /// The job's closure is bigger than the pool's
+    /// [`max_job_size`](ThreadPoolBuilder::max_job_size).
+    JobTooLarge { size: usize, max: usize },
+    /// The pool is shutting down, so a [`PoolRef`] can't submit to it anymore.
+    ShuttingDown,
+}
+
+impl fmt::Display for PoolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PoolError::NoWorkers => write!(f, "thread pool has no live workers"),
+            PoolError::QueueFull => write!(f, "thread pool queue is full"),
+            PoolError::ShuttingDown => write!(f, "thread pool is shutting down"),
+            PoolError::JobTooLarge { size, max } => {
+                write!(
+                    f,
+                    "job closure is {size} bytes, more than the {max} allowed"
+                )
+            }
+        }
+    }
+}
+
+impl error::Error for PoolError {}
+
 pub struct ThreadPool {
     workers: Vec<Worker>,
-    sender: Option<mpsc::Sender<Job>>,
+    shared: Arc<Shared>,
+    detach_on_shutdown_timeout: bool,
+    result_buffer: Option<usize>,
+    drain_mode: DrainMode,
+    watchdog_thread: Option<thread::JoinHandle<()>>,
+}
+
+/// Receives the errors of jobs submitted with [`ThreadPool::execute_fallible`].
+type ErrorHandler = Arc<dyn Fn(Box<dyn Any + Send>) + Send + Sync>;
+
+/// State shared between the pool and its workers.
+struct Shared {
+    queue: JobQueue,
+    counters: Counters,
+    /// Shared with the tracked jobs, which would keep the whole pool alive from its own queue if
+    /// they held on to `Shared`.
+    tracker: Arc<Tracker>,
+    running: Mutex<HashMap<usize, RunningJobInfo>>,
+    /// Each worker's current thread, recorded as the thread starts and removed before it exits,
+    /// so a thread listed here is still running while the lock is held.
+    worker_threads: Mutex<HashMap<usize, WorkerThread>>,
+    catch_panics: bool,
+    /// Whether jobs run on the submitting thread because there are no workers.
+    inline: bool,
+    /// Whether jobs wait in the queue for [`ThreadPool::run_pending`] instead of workers.
+    deterministic: bool,
+    /// Workers with an id below this only serve the fast lane.
+    reserved_fast_workers: usize,
+    worker_init: Option<Arc<dyn Fn(usize) + Send + Sync>>,
+    error_handler: Option<ErrorHandler>,
+    result_observer: Option<ResultObserver>,
+    event_handler: Option<EventHandler>,
+    thread_priority: ThreadPriority,
+    watchdog: Option<Arc<Watchdog>>,
+    respawn_workers: bool,
+    reject_if_no_workers: bool,
+    verbose: bool,
+    max_job_size: Option<usize>,
+    /// Set under [`ThreadPoolBuilder::recycle_job_boxes`].
+    box_pool: Option<Arc<BoxPool>>,
+    max_queued_bytes: Option<usize>,
+    /// Summed closure size of the queued jobs, only tracked under a `max_queued_bytes` budget.
+    queued_bytes: AtomicUsize,
+    /// Per-worker channels under [`DequeueStrategy::PerWorkerChannel`]. Jobs bypass `queue`.
+    ///
+    /// Read-locked while a job is handed over, so
+    /// [`ThreadPool::switch_to_per_worker_queues`] can move the queued jobs over in one go.
+    channels: RwLock<Option<Channels>>,
+    /// Held from checking the [`queue_capacity`](ThreadPoolBuilder::queue_capacity) against the
+    /// counters until the jobs are in the `channels`, so concurrent submissions can't overshoot
+    /// it. Only taken while there is a capacity.
+    admission: Mutex<()>,
+    /// Set once shutdown starts. Workers are told to stop through the queue and their channels
+    /// rather than by dropping a sender, so it works however many [`PoolRef`]s still exist.
+    stopping: AtomicBool,
+    /// Set on a [`DrainMode::RunningOnly`] shutdown so workers drop the jobs left in their
+    /// channels.
+    discard_queued: AtomicBool,
+    start_order: Arc<StartOrder>,
+    /// Worker threads that are running or about to start.
+    alive_workers: AtomicUsize,
+    /// Held when a worker thread stops counting itself in `alive_workers`, which then signals
+    /// `worker_exited`, so [`ThreadPool::shutdown_timeout`] can't miss the last exit.
+    exiting: Mutex<()>,
+    worker_exited: Condvar,
+    /// Serializes [`ThreadPool::recycle_worker`] calls.
+    recycling: Mutex<()>,
+    /// Threads blocked in [`ThreadPool::wait_with_progress`]. Finished jobs only signal
+    /// `job_completed` while there are any.
+    progress_waiters: AtomicUsize,
+    /// Held when signalling `job_completed`, so a waiter can't miss a completion between checking
+    /// the count and blocking.
+    progress: Mutex<()>,
+    job_completed: Condvar,
+}
+
+/// A job that a worker is currently running, returned by [`ThreadPool::running_jobs`].
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct RunningJob {
+    pub worker_id: usize,
+    /// Name given with [`ThreadPool::execute_named`], if any.
+    pub job_name: Option<String>,
+    pub running_for: Duration,
+}
+
+/// What became of the pool's jobs, returned by [`ThreadPool::shutdown`] and
+/// [`ThreadPool::shutdown_timeout`].
+///
+/// Like [`ThreadPool::metrics`], the counts cover the pool's whole life, or the time since the
+/// last [`ThreadPool::reset_metrics`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ShutdownReport {
+    /// Jobs that ran to completion, including those that panicked.
+    pub completed: usize,
+    /// Jobs that were still queued and never ran, either because the pool was built with
+    /// [`DrainMode::RunningOnly`] or because [`ThreadPool::shutdown_timeout`] abandoned workers.
+    /// In that case every job still queued is discarded, so the abandoned workers can't run it
+    /// later.
+    pub drained_unrun: usize,
+    /// Ids of the workers still busy when the timeout of [`ThreadPool::shutdown_timeout`]
+    /// elapsed. Always empty for [`ThreadPool::shutdown`].
+    pub still_running_at_timeout: Vec<usize>,
+}
+
+/// Describes a finished job, passed to the
+/// [`result_observer`](ThreadPoolBuilder::result_observer).
+#[derive(Clone, Debug)]
+pub struct ResultMeta {
+    /// Worker that ran the job. Always 0 for an [`inline`](ThreadPool::inline) pool.
+    pub worker_id: usize,
+    /// Name given with [`ThreadPool::execute_named`], if any.
+    pub job_name: Option<String>,
+    pub duration: Duration,
+    pub panicked: bool,
+}
+
+/// Observes every finished job, see [`ThreadPoolBuilder::result_observer`].
+type ResultObserver = Arc<dyn Fn(&ResultMeta) + Send + Sync>;
+
+/// What a worker publishes about the job it's running.
+struct RunningJobInfo {
+    job_name: Option<String>,
+    started_at: Instant,
 }
 
-type Job = Box<dyn FnOnce() + Send + 'static>;
+impl Shared {
+    /// Wraps `f` in a [`Job`], reusing an allocation from the `box_pool` if there is one.
+    fn job<F>(&self, f: F) -> Job
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        match &self.box_pool {
+            Some(box_pool) => Job::from_task(box_pool.task(f), mem::size_of::<F>()),
+            None => Job::new(f),
+        }
+    }
+
+    /// Queues `job` in `lane`, or runs it straight away on an inline pool.
+    fn enqueue(&self, job: Job, lane: Lane) -> Result<(), PoolError> {
+        self.check_job_size(job.size)?;
+
+        if self.inline {
+            self.counters.job_submitted();
+            self.run_inline(job);
+            return Ok(());
+        }
+
+        if self.reject_if_no_workers {
+            self.assert_workers_alive()?;
+        }
+
+        let size: usize = job.size;
+        self.reserve_bytes(size)?;
+
+        let channels = self.channels.read().unwrap();
+        let result: Result<(), PoolError> = match channels.as_ref() {
+            Some(channels) => self.send(channels, job),
+            None => self
+                .queue
+                .push(job, lane, &self.counters)
+                .map_err(|_| PoolError::QueueFull),
+        };
+
+        if result.is_err() {
+            self.release_bytes(size);
+        }
+        result
+    }
+
+    /// Deals `job` to one of the per-worker `channels`.
+    fn send(&self, channels: &Channels, job: Job) -> Result<(), PoolError> {
+        // Channels can't be inspected, so the capacity is checked against the counters.
+        let _admission = self.channel_admission(1)?;
+
+        // Counted first, since the worker may start the job before `send` returns.
+        self.counters.job_submitted();
+        channels.send(job).map_err(|job| {
+            self.counters.job_rejected();
+            drop(job);
+            PoolError::NoWorkers
+        })
+    }
+
+    /// Checks there's room in the `channels` for `count` more jobs under the
+    /// [`queue_capacity`](ThreadPoolBuilder::queue_capacity), if there is one.
+    ///
+    /// # Returns
+    ///
+    /// Guard to hold until the jobs have been sent, or
+    /// [`Err(PoolError::QueueFull)`](PoolError::QueueFull) if they don't fit.
+    fn channel_admission(&self, count: usize) -> Result<Option<MutexGuard<'_, ()>>, PoolError> {
+        let Some(capacity) = self.queue.capacity() else {
+            return Ok(None);
+        };
+
+        let admission = self.admission.lock().unwrap();
+        if self.counters.queued_jobs() + count > capacity {
+            return Err(PoolError::QueueFull);
+        }
+        Ok(Some(admission))
+    }
+
+    /// Runs `job` on the calling thread for a pool created with [`ThreadPool::inline`].
+    fn run_inline(&self, job: Job) {
+        self.counters.job_waited(job.queued_at.elapsed());
+        self.counters.job_started();
+        let started_at: Instant = Instant::now();
+        let panicked: bool = self.run_task(0, job.name.clone(), job.task);
+        self.job_finished(0, job.name, started_at, panicked);
+    }
+
+    /// Runs `task` for worker `worker_id`, under the watchdog if the pool has a
+    /// [`default_job_timeout`](ThreadPoolBuilder::default_job_timeout).
+    ///
+    /// # Returns
+    ///
+    /// `true` if the job panicked.
+    fn run_task(&self, worker_id: usize, job_name: Option<String>, task: Task) -> bool {
+        let Some(watchdog) = &self.watchdog else {
+            return worker::run_job(task, self.catch_panics);
+        };
+
+        let (key, token) = watchdog.watch(worker_id, job_name);
+        // Unwatched even if an uncaught panic unwinds out of the job.
+        let _unwatch = Unwatch(watchdog, key);
+        cancel::run_with(token, || worker::run_job(task, self.catch_panics))
+    }
+
+    /// Records a finished job and reports it to the result observer.
+    fn job_finished(
+        &self,
+        worker_id: usize,
+        job_name: Option<String>,
+        started_at: Instant,
+        panicked: bool,
+    ) {
+        self.counters.job_finished(panicked);
+        // Orders the relaxed completion count before the waiter check. A waiter that registers
+        // too late for this check to see it still sees the new count.
+        atomic::fence(Ordering::SeqCst);
+        if self.progress_waiters.load(Ordering::SeqCst) > 0 {
+            drop(self.progress.lock().unwrap());
+            self.job_completed.notify_all();
+        }
+
+        if let Some(result_observer) = &self.result_observer {
+            result_observer(&ResultMeta {
+                worker_id,
+                job_name,
+                duration: started_at.elapsed(),
+                panicked,
+            });
+        }
+    }
+
+    /// Passes `event` to the [`on_event`](ThreadPoolBuilder::on_event) callback, if any.
+    fn emit(&self, event: PoolEvent) {
+        if let Some(event_handler) = &self.event_handler {
+            event_handler(&event);
+        }
+    }
+
+    /// Rejects closures bigger than the [`max_job_size`](ThreadPoolBuilder::max_job_size).
+    fn check_job_size(&self, size: usize) -> Result<(), PoolError> {
+        match self.max_job_size {
+            Some(max) if size > max => Err(PoolError::JobTooLarge { size, max }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Counts `size` more bytes of queued closures against the
+    /// [`max_queued_bytes`](ThreadPoolBuilder::max_queued_bytes) budget.
+    ///
+    /// # Returns
+    ///
+    /// [`Err(PoolError::QueueFull)`](PoolError::QueueFull) if they don't fit, leaving the count
+    /// as it was.
+    fn reserve_bytes(&self, size: usize) -> Result<(), PoolError> {
+        let Some(max) = self.max_queued_bytes else {
+            return Ok(());
+        };
+
+        self.queued_bytes
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |queued| {
+                queued.checked_add(size).filter(|&total| total <= max)
+            })
+            .map(drop)
+            .map_err(|_| PoolError::QueueFull)
+    }
+
+    /// Gives back the bytes reserved for a job that left the queue or never made it in.
+    fn release_bytes(&self, size: usize) {
+        if self.max_queued_bytes.is_some() {
+            self.queued_bytes.fetch_sub(size, Ordering::SeqCst);
+        }
+    }
+
+    /// * `still_running` - Ids of the workers that didn't finish before the shutdown timeout.
+    fn shutdown_report(&self, still_running: Vec<usize>) -> ShutdownReport {
+        ShutdownReport {
+            completed: self.counters.jobs_completed(),
+            // Jobs still queued at this point sit in the channel of an abandoned worker, which
+            // discards them if it ever gets unstuck.
+            drained_unrun: self.counters.unrun_jobs(),
+            still_running_at_timeout: still_running,
+        }
+    }
+
+    /// Prints `message` if the pool is [`verbose`](ThreadPoolBuilder::verbose).
+    fn log(&self, message: fmt::Arguments<'_>) {
+        if self.verbose {
+            println!("{message}");
+        }
+    }
+
+    fn assert_workers_alive(&self) -> Result<(), PoolError> {
+        if !self.inline && !self.deterministic && self.alive_workers.load(Ordering::SeqCst) == 0 {
+            return Err(PoolError::NoWorkers);
+        }
+
+        Ok(())
+    }
+}
 
 impl ThreadPool {
     /// Creates a new ThreadPool.
-    /// 
+    ///
     /// * `thread_count` - Number of threads in the pool.
     ///
     /// # Returns
-    /// 
+    ///
     /// [`ThreadPool`]
-    /// 
+    ///
     /// # Panics
-    /// 
+    ///
     /// The `new` function will panic if the size is zero
     pub fn new(thread_count: usize) -> ThreadPool {
         assert!(thread_count > 0);
 
-        let (sender, receiver) = mpsc::channel();
-        let receiver: Arc<Mutex<mpsc::Receiver<Box<dyn FnOnce() + Send>>>> =
-            Arc::new(Mutex::new(receiver));
-        let mut workers: Vec<Worker> = Vec::with_capacity(thread_count);
-
-        for id in 0..thread_count {
-            workers.push(Worker::new(id, Arc::clone(&receiver)));
-        }
-
-        ThreadPool {
-            workers,
-            sender: Some(sender),
-        }
+        ThreadPool::from_builder(ThreadPoolBuilder::new().num_threads(thread_count))
     }
 
     /// Creates a new ThreadPool.
-    /// 
+    ///
     /// * `thread_count` - Number of threads in the pool.
-    /// 
+    ///
     /// # Returns
-    /// 
+    ///
     /// 'Result' type that represents either success ([`Ok(ThreadPool)`]) or  failure ([`Err(PoolCreationError)`])    
     pub fn build(thread_count: usize) -> Result<ThreadPool, PoolCreationError> {
-        if thread_count <= 0 {
-            return Err(PoolCreationError::LessThanOne);
-        } else {
-            let (sender, receiver) = mpsc::channel();
-            let receiver: Arc<Mutex<mpsc::Receiver<Box<dyn FnOnce() + Send>>>> =
-                Arc::new(Mutex::new(receiver));
-            let mut workers: Vec<Worker> = Vec::with_capacity(thread_count);
+        ThreadPoolBuilder::new().num_threads(thread_count).build()
+    }
+
+    /// Creates a [`ThreadPoolBuilder`] for configuring a new ThreadPool.
+    pub fn builder() -> ThreadPoolBuilder {
+        ThreadPoolBuilder::new()
+    }
+
+    /// Creates a ThreadPool without any worker threads that runs every job synchronously on the
+    /// calling thread.
+    ///
+    /// Useful for deterministic tests and for workloads too small to be worth a thread.
+    ///
+    /// # Returns
+    ///
+    /// [`ThreadPool`] whose [`ThreadPool::num_threads`] is 0.
+    pub fn inline() -> ThreadPool {
+        ThreadPool::from_builder(ThreadPoolBuilder::new().num_threads(0))
+    }
+
+    /// Spawns the workers for an already validated `builder`.
+    pub(crate) fn from_builder(mut builder: ThreadPoolBuilder) -> ThreadPool {
+        let deterministic: bool = builder.deterministic;
+        if deterministic {
+            builder.thread_count = 0;
+            builder.dequeue_strategy = DequeueStrategy::SharedQueue;
+        }
 
-            for id in 0..thread_count {
-                workers.push(Worker::new(id, Arc::clone(&receiver)));
+        let (channels, inboxes): (Option<Channels>, Vec<Inbox>) = match builder.dequeue_strategy {
+            DequeueStrategy::SharedQueue => (None, Vec::new()),
+            DequeueStrategy::PerWorkerChannel => {
+                let (channels, inboxes) = Channels::new(builder.thread_count);
+                (Some(channels), inboxes)
             }
+        };
+        let mut inboxes = inboxes.into_iter();
+        let shared: Arc<Shared> = Arc::new(Shared {
+            queue: JobQueue::new(
+                builder.queue_capacity,
+                builder
+                    .queue_backend
+                    .unwrap_or_else(|| Box::new(FifoQueue::new())),
+            ),
+            counters: Counters::default(),
+            tracker: Arc::default(),
+            running: Mutex::new(HashMap::new()),
+            worker_threads: Mutex::new(HashMap::new()),
+            catch_panics: builder.catch_panics,
+            inline: builder.thread_count == 0 && !deterministic,
+            deterministic,
+            reserved_fast_workers: builder
+                .reserved_fast_workers
+                .min(builder.thread_count.saturating_sub(1)),
+            worker_init: builder.worker_init,
+            error_handler: builder.error_handler,
+            result_observer: builder.result_observer,
+            event_handler: builder.event_handler,
+            thread_priority: builder.thread_priority,
+            watchdog: builder
+                .default_job_timeout
+                .map(|timeout| Arc::new(Watchdog::new(timeout))),
+            respawn_workers: builder.respawn_workers,
+            reject_if_no_workers: builder.reject_if_no_workers,
+            verbose: builder.verbose,
+            max_job_size: builder.max_job_size,
+            box_pool: builder.recycle_job_boxes.then(Arc::default),
+            max_queued_bytes: builder.max_queued_bytes,
+            queued_bytes: AtomicUsize::new(0),
+            channels: RwLock::new(channels),
+            admission: Mutex::new(()),
+            stopping: AtomicBool::new(false),
+            discard_queued: AtomicBool::new(false),
+            start_order: Arc::default(),
+            alive_workers: AtomicUsize::new(0),
+            exiting: Mutex::new(()),
+            worker_exited: Condvar::new(),
+            recycling: Mutex::new(()),
+            progress_waiters: AtomicUsize::new(0),
+            progress: Mutex::new(()),
+            job_completed: Condvar::new(),
+        });
+        let watchdog_thread: Option<thread::JoinHandle<()>> =
+            shared.watchdog.as_ref().map(|watchdog| {
+                let watchdog: Arc<Watchdog> = Arc::clone(watchdog);
+                let shared = Arc::downgrade(&shared);
+                thread::spawn(move || watchdog.run(shared))
+            });
+        let mut workers: Vec<Worker> = Vec::with_capacity(builder.thread_count);
+        let (ready_sender, ready) = mpsc::channel();
 
-            Ok(ThreadPool {
-                workers,
-                sender: Some(sender),
-            })
+        for id in 0..builder.thread_count {
+            let ready_sender: Option<mpsc::Sender<()>> =
+                builder.prewarm.then(|| ready_sender.clone());
+            workers.push(Worker::new(
+                id,
+                Arc::clone(&shared),
+                ready_sender,
+                inboxes.next(),
+            ));
+        }
+
+        drop(ready_sender);
+
+        if builder.prewarm {
+            // A worker whose init panicked drops its sender without signalling, so stop waiting
+            // once every sender is gone.
+            for _ in 0..builder.thread_count {
+                if ready.recv().is_err() {
+                    break;
+                }
+            }
+        }
+
+        ThreadPool {
+            workers,
+            shared,
+            detach_on_shutdown_timeout: builder.detach_on_shutdown_timeout,
+            result_buffer: builder.result_buffer,
+            drain_mode: builder.drain_mode,
+            watchdog_thread,
         }
     }
 
-    pub fn execute<F>(&self, f: F)
+    /// Runs `f` on one of the workers.
+    ///
+    /// * `f` - Closure to run.
+    ///
+    /// This used to return `()`. It now reports rejected jobs like every other way of submitting
+    /// one, so existing callers have to handle or [`unwrap`](Result::unwrap) the result.
+    ///
+    /// # Returns
+    ///
+    /// [`Err(PoolError::NoWorkers)`](PoolError::NoWorkers) if the pool was built with
+    /// [`ThreadPoolBuilder::reject_if_no_workers`] and every worker has died,
+    /// [`Err(PoolError::QueueFull)`](PoolError::QueueFull) if the
+    /// [`queue_capacity`](ThreadPoolBuilder::queue_capacity) or
+    /// [`max_queued_bytes`](ThreadPoolBuilder::max_queued_bytes) leaves no room, and
+    /// [`Err(PoolError::JobTooLarge)`](PoolError::JobTooLarge) if the closure exceeds the
+    /// [`max_job_size`](ThreadPoolBuilder::max_job_size). Pools built with the default options
+    /// never reject a job.
+    pub fn execute<F>(&self, f: F) -> Result<(), PoolError>
     where
         F: FnOnce() + Send + 'static,
     {
-        let job: Box<F> = Box::new(f);
-        self.sender.as_ref().unwrap().send(job).unwrap();
+        self.enqueue(self.shared.job(f), Lane::Back)
     }
-}
 
-impl Drop for ThreadPool {
-    fn drop(&mut self) {
-        drop(self.sender.take());
+    /// Runs a job under `name`, which shows up in [`ThreadPool::running_jobs`] while it runs.
+    ///
+    /// * `name` - Label for the job.
+    ///
+    /// * `f` - Closure to run on one of the workers.
+    pub fn execute_named<F>(&self, name: impl Into<String>, f: F) -> Result<(), PoolError>
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        self.enqueue(self.shared.job(f).named(name), Lane::Back)
+    }
+
+    /// Runs a job preferably on the same worker as every other job with the same `affinity`, so
+    /// related jobs reuse that worker's caches and thread-local data.
+    ///
+    /// * `affinity` - Key grouping related jobs, such as a hash of the data they work on.
+    ///
+    /// * `f` - Closure to run on one of the workers.
+    ///
+    /// Only a hint: it's honored under [`DequeueStrategy::PerWorkerChannel`], where the job goes
+    /// to worker `affinity % num_threads` unless that worker has died for good. A shared queue
+    /// hands the job to whichever worker is free, as with [`ThreadPool::execute`].
+    pub fn execute_with_affinity<F>(&self, affinity: u64, f: F) -> Result<(), PoolError>
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        self.enqueue(self.shared.job(f).with_affinity(affinity), Lane::Back)
+    }
+
+    /// Feeds the pool from `produce` on a dedicated thread, running `consume` on each item it
+    /// returns.
+    ///
+    /// * `produce` - Called for one item after another until it returns [`None`].
+    ///
+    /// * `consume` - Run as a job for every item.
+    ///
+    /// At most twice as many items as the pool has workers are in flight, queued or being
+    /// consumed, at a time. The producer thread blocks until a `consume` job finishes before
+    /// producing more, so memory stays bounded however fast `produce` is. It also stops once the
+    /// pool rejects an item or is dropped.
+    ///
+    /// # Returns
+    ///
+    /// [`ProducerHandle`] that can be joined to wait for every item to be consumed.
+    pub fn run_producer<T, P, F>(&self, produce: P, consume: F) -> ProducerHandle
+    where
+        T: Send + 'static,
+        P: FnMut() -> Option<T> + Send + 'static,
+        F: Fn(T) + Send + Sync + 'static,
+    {
+        let bound: usize = self.workers.len().max(1) * 2;
+
+        producer::spawn(Arc::downgrade(&self.shared), bound, produce, consume)
+    }
+
+    /// Runs a job with a context value captured at submission, such as a trace id.
+    ///
+    /// * `ctx` - Value handed to `f` by reference on the worker, then dropped once `f` returns.
+    ///
+    /// * `f` - Closure to run on one of the workers.
+    pub fn execute_with_context<C, F>(&self, ctx: C, f: F) -> Result<(), PoolError>
+    where
+        C: Send + 'static,
+        F: FnOnce(&C) + Send + 'static,
+    {
+        self.execute(move || f(&ctx))
+    }
+
+    /// Runs a job that starts only after every job submitted before it with this method has
+    /// started.
+    ///
+    /// * `f` - Closure to run on one of the workers.
+    ///
+    /// Ordered jobs may still run at the same time, they just begin in submission order. Jobs are
+    /// queued in the order of their turns, even when submitted from several threads at once. A
+    /// worker that takes an ordered job early waits for its turn, so this relies on jobs being
+    /// taken in the order they were queued: avoid mixing it with
+    /// [`execute_front`](ThreadPool::execute_front) or a reordering
+    /// [`queue_backend`](ThreadPoolBuilder::queue_backend). An ordered job that's dropped without
+    /// running gives up its turn.
+    pub fn execute_ordered<F>(&self, f: F) -> Result<(), PoolError>
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        // An inline pool runs the job right here, and the job may submit another ordered job.
+        let _submitting = (!self.shared.inline).then(|| self.shared.start_order.lock_submissions());
+        let ticket: Ticket = self.shared.start_order.ticket();
+
+        self.execute(move || {
+            ticket.wait_turn();
+            drop(ticket);
+            f();
+        })
+    }
+
+    /// Queues every job in `jobs`, or none of them if the
+    /// [`queue_capacity`](ThreadPoolBuilder::queue_capacity) or
+    /// [`max_queued_bytes`](ThreadPoolBuilder::max_queued_bytes) doesn't leave room for all of
+    /// them.
+    ///
+    /// * `jobs` - Closures to run on the workers, queued in order.
+    ///
+    /// # Returns
+    ///
+    /// [`Err`] with every job if the batch was rejected: for lack of room, because the jobs exceed
+    /// the [`max_job_size`](ThreadPoolBuilder::max_job_size), or because the pool was built with
+    /// [`ThreadPoolBuilder::reject_if_no_workers`] and every worker has died. Under
+    /// [`DequeueStrategy::PerWorkerChannel`] the batch is also rejected once every worker has died
+    /// for good, since no channel is left to take it.
+    pub fn try_execute_all<I, F>(&self, jobs: I) -> Result<(), Vec<F>>
+    where
+        I: IntoIterator<Item = F>,
+        F: FnOnce() + Send + 'static,
+    {
+        let jobs: Vec<F> = jobs.into_iter().collect();
+
+        if self.shared.check_job_size(mem::size_of::<F>()).is_err() {
+            return Err(jobs);
+        }
+
+        if self.shared.inline {
+            for job in jobs {
+                self.shared.counters.job_submitted();
+                self.shared.run_inline(self.shared.job(job));
+            }
+            return Ok(());
+        }
+
+        if self.shared.reject_if_no_workers && self.shared.assert_workers_alive().is_err() {
+            return Err(jobs);
+        }
+
+        let size: usize = mem::size_of::<F>();
+        let total: usize = size.saturating_mul(jobs.len());
+        if self.shared.reserve_bytes(total).is_err() {
+            return Err(jobs);
+        }
 
-        for worker in &mut self.workers {
-            println!("Shutting down worker {}", worker.id);
+        // Held until every job has been dealt, so no inbox can close in the middle of the batch.
+        let channels = self.shared.channels.read().unwrap();
+        if let Some(channels) = channels.as_ref() {
+            let admission = self.shared.channel_admission(jobs.len());
+            if admission.is_err() || !channels.is_open() {
+                self.shared.release_bytes(total);
+                return Err(jobs);
+            }
 
-            if let Some(thread) = worker.thread.take() {
-                thread.join().unwrap();
+            for job in jobs {
+                self.shared.counters.job_submitted();
+                if channels.send(self.shared.job(job)).is_err() {
+                    unreachable!("an open inbox closed while a batch was dealt");
+                }
             }
+            return Ok(());
         }
+
+        self.shared
+            .queue
+            .push_all(jobs, |job| self.shared.job(job), &self.shared.counters)
+            .inspect_err(|_| self.shared.release_bytes(total))
     }
-}
 
-struct Worker {
-    id: usize,
-    thread: Option<thread::JoinHandle<()>>,
-}
+    /// Queues a job ahead of every job that is already waiting, so it runs next.
+    ///
+    /// * `f` - Closure to run on one of the workers.
+    ///
+    /// Jobs queued this way are taken in last-in, first-out order. Overusing it starves the jobs
+    /// at the back of the queue.
+    ///
+    /// Under [`DequeueStrategy::PerWorkerChannel`] the job can't jump ahead: it's dealt out like
+    /// any other and runs after the jobs already dealt to its worker.
+    pub fn execute_front<F>(&self, f: F) -> Result<(), PoolError>
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        self.enqueue(self.shared.job(f), Lane::Front)
+    }
+
+    /// Runs a job in the fast lane, ahead of every bulk job.
+    ///
+    /// * `f` - Closure to run on one of the workers.
+    ///
+    /// Fast jobs can also run on the workers reserved with
+    /// [`ThreadPoolBuilder::reserved_fast_workers`], so they start promptly even while the bulk
+    /// lane is backed up.
+    ///
+    /// Under [`DequeueStrategy::PerWorkerChannel`] there is no fast lane: the job is dealt out
+    /// like any other and waits behind the jobs already dealt to its worker.
+    pub fn execute_fast<F>(&self, f: F) -> Result<(), PoolError>
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        self.enqueue(self.shared.job(f), Lane::Fast)
+    }
+
+    /// Queues `job` in `lane`, or runs it straight away on an inline pool.
+    fn enqueue(&self, job: Job, lane: Lane) -> Result<(), PoolError> {
+        self.shared.enqueue(job, lane)
+    }
+
+    /// Checks that at least one worker thread is alive to run queued jobs.
+    ///
+    /// Workers only die for good when a panic escapes them and
+    /// [`ThreadPoolBuilder::respawn_workers`] is disabled.
+    ///
+    /// # Returns
+    ///
+    /// [`Err(PoolError::NoWorkers)`](PoolError::NoWorkers) if every worker has died. Always
+    /// [`Ok`] for an [`inline`](ThreadPool::inline) pool.
+    pub fn assert_workers_alive(&self) -> Result<(), PoolError> {
+        self.shared.assert_workers_alive()
+    }
+
+    /// Blocks until at least `n` workers are waiting for a job at the same time.
+    ///
+    /// A worker only counts as idle once there's nothing in the queue for it to take, so when all
+    /// of the workers are idle every job submitted so far has finished.
+    ///
+    /// * `n` - Number of idle workers to wait for. Clamped to [`ThreadPool::num_threads`], so an
+    ///   [`inline`](ThreadPool::inline) pool returns straight away.
+    ///
+    /// Workers reserved with [`ThreadPoolBuilder::reserved_fast_workers`] count as idle while they
+    /// wait for fast jobs. Workers that died without being respawned never become idle again.
+    /// A [`deterministic`](ThreadPoolBuilder::deterministic) pool has no workers and returns
+    /// straight away too, even with jobs queued for [`ThreadPool::run_pending`].
+    pub fn wait_for_idle_workers(&self, n: usize) {
+        if self.shared.deterministic {
+            return;
+        }
+
+        self.shared
+            .queue
+            .wait_for_idle(n.min(self.num_threads()), &self.shared.counters);
+    }
 
-impl Worker {
-    /// Creates a new Worker.
-    /// 
-    /// `id` - ID of  the worker.
-    /// 
-    /// `receiver` - [`mpsc::Receiver<T>`] where `T` is [`Job`]. Receiver wrapped in [`Mutex`] and
-    /// [`Arc`] structs.
+    /// Blocks until `total` jobs have completed, reporting every step along the way.
+    ///
+    /// * `total` - Number of completed jobs to wait for, counted like
+    ///   [`Metrics::jobs_completed`], since the pool was created or its metrics were reset.
+    ///
+    /// * `on_progress` - Called on the calling thread with `(completed, total)` once straight
+    ///   away, then whenever the count advances, such as to drive a progress bar. Several jobs
+    ///   finishing in quick succession may be reported at once. The last call is
+    ///   `(total, total)`.
     ///
     /// # Panics
     ///
-    /// This 'new' function will panic. I just don't know on what yet.
-    fn new(id: usize, receiver: Arc<Mutex<mpsc::Receiver<Job>>>) -> Worker {
-        let thread: JoinHandle<()> = thread::spawn(move || loop {
-            let message: Result<Box<dyn FnOnce() + Send>, mpsc::RecvError> =
-                receiver.lock().unwrap().recv();
+    /// On a [`deterministic`](ThreadPoolBuilder::deterministic) pool, if fewer than `total` jobs
+    /// have completed: they only run in [`ThreadPool::run_pending`], so this would never return.
+    pub fn wait_with_progress(&self, total: usize, on_progress: impl Fn(usize, usize)) {
+        let completed = || self.shared.counters.jobs_completed().min(total);
+        assert!(
+            !self.shared.deterministic || completed() == total,
+            "waiting for jobs that only run_pending can run"
+        );
+        self.shared.progress_waiters.fetch_add(1, Ordering::SeqCst);
+        let _waiting = ProgressWaiter(&self.shared.progress_waiters);
+        let mut reported: Option<usize> = None;
 
-            match message {
-                Ok(job) => {
-                    println!("Worker {id} got a job; executing.");
+        loop {
+            let progress = self.shared.progress.lock().unwrap();
+            drop(
+                self.shared
+                    .job_completed
+                    .wait_while(progress, |_| reported == Some(completed()))
+                    .unwrap(),
+            );
 
-                    job();
-                }
-                Err(_) => {
-                    println!("Worker {id} disconnected; shutting down.");
-                    break;
-                }
+            // Reported without the lock, so a slow callback doesn't hold up the workers.
+            let count: usize = completed();
+            on_progress(count, total);
+            if count == total {
+                return;
             }
-        });
-
-        Worker {
-            id,
-            thread: Some(thread),
+            reported = Some(count);
         }
     }
-}
 
-#[cfg(test)]
-mod test {
-    use super::*;
+    /// Runs a fallible job, passing any error it returns to the
+    /// [`error_handler`](ThreadPoolBuilder::error_handler).
+    ///
+    /// * `f` - Closure to run on one of the workers. Its success value is discarded; use
+    ///   [`ThreadPool::submit`] to keep it.
+    pub fn execute_fallible<F, T, E>(&self, f: F) -> Result<(), PoolError>
+    where
+        F: FnOnce() -> Result<T, E> + Send + 'static,
+        E: Send + 'static,
+    {
+        let error_handler: Option<ErrorHandler> = self.shared.error_handler.clone();
 
-    #[test]
-    fn test_create_threadpool_valid() {
-        let threadpool: ThreadPool = ThreadPool::new(4);
+        self.execute(move || {
+            if let (Err(err), Some(error_handler)) = (f(), error_handler) {
+                error_handler(Box::new(err));
+            }
+        })
+    }
 
-        assert_eq!(threadpool.workers.len(), 4);
+    /// Runs a job followed by a cleanup step that runs even if the job panics.
+    ///
+    /// * `f` - Closure to run on one of the workers.
+    ///
+    /// * `cleanup` - Closure run on the same worker right after `f` returns or panics. A panic in
+    ///   `f` is re-raised once `cleanup` is done, so the worker still counts it.
+    ///
+    /// Neither closure runs if the job is rejected.
+    pub fn execute_with_cleanup<F, C>(&self, f: F, cleanup: C) -> Result<(), PoolError>
+    where
+        F: FnOnce() + Send + 'static,
+        C: FnOnce() + Send + 'static,
+    {
+        self.execute(move || {
+            let result: thread::Result<()> = panic::catch_unwind(AssertUnwindSafe(f));
+            cleanup();
+
+            if let Err(payload) = result {
+                panic::resume_unwind(payload);
+            }
+        })
+    }
+
+    /// Runs a job whose timing can be queried with [`ThreadPool::timing`].
+    ///
+    /// * `f` - Closure to run on one of the workers.
+    ///
+    /// # Returns
+    ///
+    /// [`JobId`] identifying the job. Its timing is kept for the lifetime of the pool.
+    pub fn execute_tracked<F>(&self, f: F) -> Result<JobId, PoolError>
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        let id: JobId = self.shared.tracker.submitted();
+        let tracker: Arc<Tracker> = Arc::clone(&self.shared.tracker);
+
+        self.execute(move || {
+            tracker.started(id);
+            // Record the finish even if `f` panics.
+            let _finished = FinishGuard(&tracker, id);
+            f();
+        })?;
+
+        Ok(id)
+    }
+
+    /// Submits a job whose return value is collected by [`ThreadPool::shutdown_collect`].
+    ///
+    /// * `f` - Closure to run on one of the workers.
+    ///
+    /// The job is tracked like one submitted with [`ThreadPool::execute_tracked`], so its timing
+    /// can be queried too.
+    ///
+    /// # Returns
+    ///
+    /// [`JobId`] that its result is keyed by.
+    pub fn submit_tracked<F, T>(&self, f: F) -> Result<JobId, PoolError>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let id: JobId = self.shared.tracker.submitted();
+        let tracker: Arc<Tracker> = Arc::clone(&self.shared.tracker);
+
+        self.execute(move || {
+            tracker.started(id);
+            let result: thread::Result<T> = {
+                let _finished = FinishGuard(&tracker, id);
+                panic::catch_unwind(AssertUnwindSafe(f))
+            };
+            let panicked: bool = result.is_err();
+            tracker.record_result(
+                id,
+                result.map(|value| Box::new(value) as Box<dyn Any + Send>),
+            );
+
+            // The payload went to the results; re-raise so the worker still counts the panic.
+            if panicked {
+                panic::resume_unwind(Box::new("job panicked"));
+            }
+        })?;
+
+        Ok(id)
+    }
+
+    /// # Returns
+    ///
+    /// [`JobTiming`] of a job submitted with [`ThreadPool::execute_tracked`], or [`None`] if
+    /// `id` is unknown.
+    pub fn timing(&self, id: JobId) -> Option<JobTiming> {
+        self.shared.tracker.timing(id)
+    }
+
+    /// Submits a job whose return value can be collected later.
+    ///
+    /// * `f` - Closure to run on one of the workers.
+    ///
+    /// # Returns
+    ///
+    /// [`JobHandle<T>`] that yields the closure's return value, or a [`JoinError`] if the
+    /// closure panicked or couldn't be queued.
+    pub fn submit<F, T>(&self, f: F) -> JobHandle<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let (promise, handle) = handle::promise(Arc::downgrade(&self.shared));
+        let _ = handle::submit_into(&self.shared, promise, f);
+
+        handle
+    }
+
+    /// Submits speculative work that is skipped if nobody waits for its result anymore.
+    ///
+    /// * `f` - Closure to run on one of the workers.
+    ///
+    /// If the returned handle is dropped before a worker takes the job, and no follow-up job was
+    /// chained onto it with [`JobHandle::then`], the job is skipped. Once the job has started it
+    /// runs to completion regardless.
+    ///
+    /// # Returns
+    ///
+    /// [`JobHandle<T>`] that yields the closure's return value, like [`ThreadPool::submit`].
+    pub fn execute_weak<F, T>(&self, f: F) -> JobHandle<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let (promise, handle) = handle::promise(Arc::downgrade(&self.shared));
+        let _ = handle::submit_into(&self.shared, promise.skip_if_abandoned(), f);
+
+        handle
+    }
+
+    /// Maps `f` over `iter` on the workers, pulling items only as results are consumed.
+    ///
+    /// * `iter` - Items to map. It may be too large to hold in memory, or unbounded.
+    ///
+    /// * `f` - Closure run on one of the workers for each item.
+    ///
+    /// At most twice as many items as the pool has workers are in flight at once, so a slow
+    /// consumer holds back submission instead of letting the queue grow.
+    ///
+    /// # Returns
+    ///
+    /// Iterator over `f`'s results, in the order of `iter`. It re-raises the panic of any job
+    /// whose `f` panicked.
+    pub fn par_map_iter<'a, I, R, F>(&'a self, iter: I, f: F) -> impl Iterator<Item = R> + 'a
+    where
+        I: IntoIterator,
+        I::IntoIter: 'a,
+        I::Item: Send + 'static,
+        F: Fn(I::Item) -> R + Send + Sync + 'static,
+        R: Send + 'static,
+    {
+        par_iter::ParMapIter::new(self, iter.into_iter(), f)
+    }
+
+    /// Drives `fut` to completion on one of the workers.
+    ///
+    /// * `fut` - Future to run. It's polled by a minimal executor that parks the worker thread
+    ///   until the future's waker is called, so it must not rely on a particular async runtime.
+    ///
+    /// # Returns
+    ///
+    /// [`JobHandle`] that yields the future's output.
+    pub fn block_on_future<F>(&self, fut: F) -> JobHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        self.submit(move || executor::block_on(fut))
+    }
+
+    /// Creates a channel for collecting results with [`ThreadPool::execute_into`].
+    ///
+    /// # Returns
+    ///
+    /// A ([`ResultSender<T>`], [`mpsc::Receiver<T>`]) pair. The channel is bounded if the pool
+    /// was built with [`ThreadPoolBuilder::result_buffer`].
+    pub fn result_channel<T>(&self) -> (ResultSender<T>, mpsc::Receiver<T>) {
+        ResultSender::channel(self.result_buffer)
+    }
+
+    /// Runs `f` on one of the workers and sends its return value to `results`.
+    ///
+    /// * `f` - Closure to run on one of the workers.
+    ///
+    /// * `results` - Sender created by [`ThreadPool::result_channel`].
+    ///
+    /// The result is discarded if the receiver has been dropped.
+    pub fn execute_into<F, T>(&self, f: F, results: &ResultSender<T>) -> Result<(), PoolError>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let results: ResultSender<T> = results.clone();
+
+        self.execute(move || {
+            let _ = results.send(f());
+        })
+    }
+
+    /// Runs every queued job on the calling thread, one at a time, for a pool built with
+    /// [`ThreadPoolBuilder::deterministic`].
+    ///
+    /// Jobs are taken in the order workers would take them. Jobs queued by the running jobs run
+    /// too, before this returns. Does nothing on other pools, whose jobs belong to the workers.
+    pub fn run_pending(&self) {
+        if !self.shared.deterministic {
+            return;
+        }
+
+        while let Some(job) = self.shared.queue.try_pop() {
+            self.shared.release_bytes(job.size);
+            self.shared.run_inline(job);
+        }
+    }
+
+    /// # Returns
+    ///
+    /// Number of worker threads in the pool.
+    pub fn num_threads(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Moves a pool using [`DequeueStrategy::SharedQueue`] over to
+    /// [`DequeueStrategy::PerWorkerChannel`] without losing any queued job.
+    ///
+    /// The queued jobs are dealt out to the new channels in the order they would have run, and
+    /// new jobs go to the channels from then on. Each worker then finishes its current job and is
+    /// replaced by a thread reading its channel, which runs the
+    /// [`worker_init`](ThreadPoolBuilder::worker_init) hook again. Workers are replaced one by one
+    /// as they finish, so a long job only holds up its own worker. Blocks until every worker has
+    /// been replaced. Does nothing if the pool already uses per-worker channels, is
+    /// [`inline`](ThreadPool::inline) or is [`deterministic`](ThreadPoolBuilder::deterministic).
+    pub fn switch_to_per_worker_queues(&mut self) {
+        if self.shared.inline
+            || self.shared.deterministic
+            || self.shared.channels.read().unwrap().is_some()
+        {
+            return;
+        }
+
+        let (channels, inboxes) = Channels::new(self.workers.len());
+        {
+            // Hold off submissions so none land in the queue after it has been emptied.
+            let mut installed = self.shared.channels.write().unwrap();
+            for job in self.shared.queue.take_all() {
+                // Can't fail: the receiving ends are still in `inboxes`.
+                let _ = channels.send(job);
+            }
+            *installed = Some(channels);
+        }
+
+        for worker in &self.workers {
+            worker.retire();
+        }
+        self.shared.queue.wake_all();
+
+        // Each worker is back as soon as its own job is done, while the others finish theirs.
+        let shared: &Arc<Shared> = &self.shared;
+        thread::scope(|scope| {
+            for (worker, inbox) in self.workers.iter().zip(inboxes) {
+                scope.spawn(move || {
+                    worker.join();
+                    worker.restart(shared, Some(inbox));
+                });
+            }
+        });
+    }
+
+    /// Replaces a worker's thread with a fresh one, re-running the
+    /// [`worker_init`](ThreadPoolBuilder::worker_init) hook.
+    ///
+    /// * `worker_id` - ID of the worker to recycle. Does nothing if no worker has that id.
+    ///
+    /// Blocks until the worker has finished its current job. Only one worker is recycled at a
+    /// time, so the rest of the pool keeps processing jobs meanwhile.
+    pub fn recycle_worker(&self, worker_id: usize) {
+        let _recycling = self.shared.recycling.lock().unwrap();
+
+        if let Some(worker) = self.workers.iter().find(|worker| worker.id == worker_id) {
+            worker.recycle(&self.shared);
+        }
+    }
+
+    /// Shrinks the pool by up to `n` workers, letting each finish its current job first.
+    ///
+    /// * `n` - Number of workers to remove, starting from the highest id.
+    ///
+    /// The last worker, and the last one not reserved for the fast lane, are never removed, so
+    /// every queued job still has a worker to run it. Workers can't be added back, so a pool with
+    /// none left could never run another job; drop it or shut it down instead. Under
+    /// [`DequeueStrategy::PerWorkerChannel`] a removed worker first runs the jobs already dealt to
+    /// it, and this blocks until it has. Jobs dealt to it while it does are dealt to the others.
+    ///
+    /// # Returns
+    ///
+    /// How many workers were removed.
+    pub fn remove_workers(&mut self, n: usize) -> usize {
+        let keep: usize = (self.shared.reserved_fast_workers + 1).min(self.workers.len());
+        let count: usize = n.min(self.workers.len() - keep);
+        let removed: Vec<Worker> = self.workers.split_off(self.workers.len() - count);
+
+        for worker in &removed {
+            match self.shared.channels.read().unwrap().as_ref() {
+                Some(channels) => channels.stop(worker.id),
+                None => worker.retire(),
+            }
+        }
+        self.shared.queue.wake_all();
+
+        for worker in &removed {
+            self.shared
+                .log(format_args!("Removing worker {}", worker.id));
+
+            if let Some(inbox) = worker.join() {
+                let left: Vec<Job> = {
+                    // Hold off submissions so none is sent to the inbox while it's emptied.
+                    let mut sending = self.shared.channels.write().unwrap();
+                    if let Some(channels) = sending.as_mut() {
+                        channels.close(worker.id);
+                    }
+                    dispatch::close_inbox(inbox)
+                };
+
+                let channels = self.shared.channels.read().unwrap();
+                for job in left {
+                    if let Err(job) = channels.as_ref().unwrap().send(job) {
+                        self.shared.counters.job_rejected();
+                        self.shared.release_bytes(job.size);
+                    }
+                }
+            }
+        }
+
+        count
+    }
+
+    /// Lists the jobs that are waiting for a worker, without removing them.
+    ///
+    /// # Returns
+    ///
+    /// The job names in the order workers will take them. Jobs that weren't submitted with
+    /// [`ThreadPool::execute_named`] are listed as `<unnamed>`.
+    pub fn queued_job_names(&self) -> Vec<String> {
+        self.shared.queue.names()
+    }
+
+    /// Lists the jobs that are running right now.
+    ///
+    /// # Returns
+    ///
+    /// One [`RunningJob`] per busy worker, ordered by worker id.
+    pub fn running_jobs(&self) -> Vec<RunningJob> {
+        let now: Instant = Instant::now();
+        let mut jobs: Vec<RunningJob> = self
+            .shared
+            .running
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(&worker_id, info)| RunningJob {
+                worker_id,
+                job_name: info.job_name.clone(),
+                running_for: now.saturating_duration_since(info.started_at),
+            })
+            .collect();
+
+        jobs.sort_by_key(|job| job.worker_id);
+        jobs
+    }
+
+    /// Maps each worker to the OS id of its thread, to line workers up with the output of
+    /// profilers such as `perf`.
+    ///
+    /// On Linux this is the kernel thread id and on Windows the Win32 thread id. Elsewhere it's
+    /// a unique number the OS doesn't know about. A worker whose thread was replaced reports the
+    /// replacement's id. A worker that hasn't started yet is missing; build the pool with
+    /// [`ThreadPoolBuilder::prewarm`] to have every worker listed straight away.
+    ///
+    /// # Returns
+    ///
+    /// `(worker_id, os_thread_id)` pairs ordered by worker id.
+    pub fn os_thread_ids(&self) -> Vec<(usize, u64)> {
+        let mut ids: Vec<(usize, u64)> = self
+            .shared
+            .worker_threads
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(&worker_id, thread)| (worker_id, thread.os_id))
+            .collect();
+
+        ids.sort_unstable();
+        ids
+    }
+
+    /// Reads how much CPU time each worker's thread has used, to tell workers doing heavy
+    /// computation from those mostly blocked on I/O.
+    ///
+    /// On 64-bit Linux this is the CPU time the kernel accounts to the thread. Elsewhere it falls
+    /// back to the wall-clock time the worker spent running jobs, blocked or not. A worker whose
+    /// thread was replaced only reports the replacement's CPU time.
+    ///
+    /// # Returns
+    ///
+    /// `(worker_id, cpu_time)` pairs ordered by worker id.
+    pub fn worker_cpu_times(&self) -> Vec<(usize, Duration)> {
+        // Held while reading: threads leave the map before exiting, so each clock read here
+        // belongs to a running thread.
+        let threads = self.shared.worker_threads.lock().unwrap();
+
+        self.workers
+            .iter()
+            .map(|worker| {
+                let cpu_time: Option<Duration> =
+                    threads.get(&worker.id).and_then(WorkerThread::cpu_time);
+                (worker.id, cpu_time.unwrap_or_else(|| worker.busy_time()))
+            })
+            .collect()
+    }
+
+    /// Takes a snapshot of the pool's counters.
+    ///
+    /// # Returns
+    ///
+    /// [`Metrics`]
+    pub fn metrics(&self) -> Metrics {
+        self.shared.counters.snapshot()
+    }
+
+    /// Reads the metrics at a single point in time.
+    ///
+    /// Unlike [`ThreadPool::metrics`], whose counters are read one by one while jobs keep
+    /// moving, this briefly stalls every submission, job pickup and job completion while the
+    /// counters are read. Use it sparingly on busy pools.
+    ///
+    /// # Returns
+    ///
+    /// [`PoolSnapshot`] whose queued, active and completed jobs add up to the submitted ones.
+    pub fn snapshot_consistent(&self) -> PoolSnapshot {
+        self.shared.counters.consistent_snapshot()
+    }
+
+    /// Rolling average of how long jobs waited in the queue before a worker started them.
+    ///
+    /// Recent jobs weigh the most, so the average follows changes in load within a few dozen
+    /// jobs. One that keeps growing means the pool has too few workers for its load.
+    ///
+    /// # Returns
+    ///
+    /// The average wait, or [`Duration::ZERO`] if no job has started since the pool was
+    /// created or its metrics were reset.
+    pub fn avg_queue_latency(&self) -> Duration {
+        self.shared.counters.avg_queue_wait()
+    }
+
+    /// Zeroes the cumulative counters so later [`ThreadPool::metrics`] calls only reflect
+    /// activity after the reset.
+    ///
+    /// The `active_jobs` and `queued_jobs` gauges describe live state and are not reset.
+    pub fn reset_metrics(&self) {
+        self.shared.counters.reset();
+    }
+
+    /// Shuts the pool down, waiting for the workers to finish.
+    ///
+    /// Queued jobs are run or discarded according to the pool's
+    /// [`drain_mode`](ThreadPoolBuilder::drain_mode). Dropping the pool does the same.
+    ///
+    /// # Returns
+    ///
+    /// How many jobs completed and how many were discarded unrun.
+    pub fn shutdown(self) -> ShutdownReport {
+        let shared: Arc<Shared> = Arc::clone(&self.shared);
+        drop(self);
+
+        shared.shutdown_report(Vec::new())
+    }
+
+    /// Shuts the pool down like [`ThreadPool::shutdown`], then collects the results of the jobs
+    /// submitted with [`ThreadPool::submit_tracked`].
+    ///
+    /// Jobs the pool's [`DrainMode`] discarded never ran and are left out.
+    ///
+    /// # Returns
+    ///
+    /// Each job's [`JobId`] with its return value, or its panic payload, ordered by [`JobId`].
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if a tracked job returned something other than a `T`.
+    pub fn shutdown_collect<T: 'static>(self) -> Vec<(JobId, thread::Result<T>)> {
+        let shared: Arc<Shared> = Arc::clone(&self.shared);
+        drop(self);
+
+        shared
+            .tracker
+            .take_results()
+            .into_iter()
+            .map(|(id, result)| {
+                let result: thread::Result<T> = result.map(|value| {
+                    *value
+                        .downcast::<T>()
+                        .expect("tracked jobs returned a different type")
+                });
+                (id, result)
+            })
+            .collect()
+    }
+
+    /// Stops the queue handing out jobs beyond what the pool's [`DrainMode`] allows.
+    fn close(&self) {
+        self.shared.stopping.store(true, Ordering::SeqCst);
+        if self.drain_mode == DrainMode::RunningOnly {
+            self.shared.discard_queued.store(true, Ordering::SeqCst);
+        }
+        if let Some(channels) = self.shared.channels.read().unwrap().as_ref() {
+            channels.stop_all();
+        }
+
+        let discarded: usize = self.shared.queue.close(self.drain_mode);
+        self.shared.counters.jobs_discarded(discarded);
+    }
+
+    /// Shuts the pool down, waiting at most `timeout` for the workers to finish.
+    ///
+    /// * `timeout` - How long to wait for the queued and running jobs to finish.
+    ///
+    /// If the timeout elapses and the pool was built with
+    /// [`ThreadPoolBuilder::detach_on_shutdown_timeout`], the workers that are still busy are
+    /// abandoned so this call returns promptly, and the jobs still queued are discarded as under
+    /// [`DrainMode::RunningOnly`]. Otherwise the remaining workers are joined and this call
+    /// blocks until they finish.
+    ///
+    /// # Returns
+    ///
+    /// How many jobs completed and how many were discarded unrun, along with the workers that
+    /// didn't finish within `timeout`.
+    pub fn shutdown_timeout(self, timeout: Duration) -> ShutdownReport {
+        self.close();
+
+        let exiting = self.shared.exiting.lock().unwrap();
+        let (exiting, wait) = self
+            .shared
+            .worker_exited
+            .wait_timeout_while(exiting, timeout, |_| {
+                self.shared.alive_workers.load(Ordering::SeqCst) > 0
+            })
+            .unwrap();
+        drop(exiting);
+
+        // Every worker has stopped counting itself once the wait succeeds, and its thread is
+        // about to return.
+        let still_running: Vec<usize> = if wait.timed_out() {
+            self.workers
+                .iter()
+                .filter(|worker| !worker.is_finished())
+                .map(|worker| worker.id)
+                .collect()
+        } else {
+            Vec::new()
+        };
+        if self.detach_on_shutdown_timeout && !still_running.is_empty() {
+            // Nobody else is left to run them, and the abandoned workers must not once they're
+            // done with their current job.
+            self.shared.discard_queued.store(true, Ordering::SeqCst);
+            let discarded: usize = self.shared.queue.close(DrainMode::RunningOnly);
+            self.shared.counters.jobs_discarded(discarded);
+
+            for worker in &self.workers {
+                if still_running.contains(&worker.id) {
+                    self.shared.log(format_args!(
+                        "Abandoning worker {} after shutdown timeout",
+                        worker.id
+                    ));
+                    worker.detach();
+                }
+            }
+        }
+
+        let shared: Arc<Shared> = Arc::clone(&self.shared);
+        drop(self);
+
+        shared.shutdown_report(still_running)
+    }
+}
+
+/// Marks a tracked job as finished when dropped.
+struct FinishGuard<'a>(&'a Tracker, JobId);
+
+impl Drop for FinishGuard<'_> {
+    fn drop(&mut self) {
+        self.0.finished(self.1);
+    }
+}
+
+/// Unregisters a [`ThreadPool::wait_with_progress`] caller when dropped.
+struct ProgressWaiter<'a>(&'a AtomicUsize);
+
+impl Drop for ProgressWaiter<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Stops the watchdog from timing out a job when dropped.
+struct Unwatch<'a>(&'a Watchdog, u64);
+
+impl Drop for Unwatch<'_> {
+    fn drop(&mut self) {
+        self.0.unwatch(self.1);
+    }
+}
+
+impl Drop for ThreadPool {
+    fn drop(&mut self) {
+        self.close();
+
+        for worker in &self.workers {
+            self.shared
+                .log(format_args!("Shutting down worker {}", worker.id));
+
+            worker.join();
+        }
+
+        if let Some(watchdog) = &self.shared.watchdog {
+            watchdog.stop();
+        }
+        if let Some(watchdog_thread) = self.watchdog_thread.take() {
+            let _ = watchdog_thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_create_threadpool_valid() {
+        let threadpool: ThreadPool = ThreadPool::new(4);
+
+        assert_eq!(threadpool.workers.len(), 4);
+    }
+
+    #[test]
+    fn test_pool_creation_error_converts_to_anyhow() {
+        fn make() -> anyhow::Result<ThreadPool> {
+            Ok(ThreadPool::build(0)?)
+        }
+
+        let err: anyhow::Error = make().err().unwrap();
+        assert_eq!(
+            err.downcast_ref::<PoolCreationError>(),
+            Some(&PoolCreationError::LessThanOne)
+        );
+        assert_eq!(err.to_string(), "thread pool must have at least one thread");
+    }
+
+    #[test]
+    fn test_shutdown_timeout_detaches_stuck_worker() {
+        let threadpool: ThreadPool = ThreadPool::builder()
+            .num_threads(2)
+            .detach_on_shutdown_timeout(true)
+            .build()
+            .unwrap_or_else(|_| panic!("failed to build pool"));
+
+        threadpool
+            .execute(|| loop {
+                thread::sleep(Duration::from_secs(1));
+            })
+            .unwrap();
+
+        let start: Instant = Instant::now();
+        let report: ShutdownReport = threadpool.shutdown_timeout(Duration::from_millis(100));
+        assert_eq!(report.still_running_at_timeout.len(), 1);
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_shutdown_timeout_returns_once_workers_exit() {
+        let threadpool: ThreadPool = ThreadPool::new(4);
+        for _ in 0..8 {
+            threadpool
+                .execute(|| thread::sleep(Duration::from_millis(20)))
+                .unwrap();
+        }
+
+        let start: Instant = Instant::now();
+        let report: ShutdownReport = threadpool.shutdown_timeout(Duration::from_secs(30));
+
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert!(report.still_running_at_timeout.is_empty());
+        assert_eq!(report.completed, 8);
+    }
+
+    #[test]
+    fn test_shutdown_timeout_discards_jobs_queued_for_abandoned_workers() {
+        for strategy in [
+            DequeueStrategy::SharedQueue,
+            DequeueStrategy::PerWorkerChannel,
+        ] {
+            let threadpool: ThreadPool = ThreadPool::builder()
+                .num_threads(1)
+                .dequeue_strategy(strategy)
+                .detach_on_shutdown_timeout(true)
+                .build()
+                .unwrap();
+            let (started_sender, started) = mpsc::channel::<()>();
+            let (release, released) = mpsc::channel::<()>();
+            let (unstuck_sender, unstuck) = mpsc::channel::<()>();
+            let ran: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+
+            threadpool
+                .execute(move || {
+                    started_sender.send(()).unwrap();
+                    let _ = released.recv();
+                    unstuck_sender.send(()).unwrap();
+                })
+                .unwrap();
+            started.recv().unwrap();
+            for _ in 0..3 {
+                let ran = Arc::clone(&ran);
+                threadpool
+                    .execute(move || {
+                        ran.fetch_add(1, Ordering::SeqCst);
+                    })
+                    .unwrap();
+            }
+
+            let report: ShutdownReport = threadpool.shutdown_timeout(Duration::from_millis(50));
+            assert_eq!(report.still_running_at_timeout, vec![0]);
+            assert_eq!(report.drained_unrun, 3);
+
+            // The abandoned worker finishes its job but leaves the discarded ones be.
+            drop(release);
+            unstuck.recv().unwrap();
+            thread::sleep(Duration::from_millis(50));
+            assert_eq!(ran.load(Ordering::SeqCst), 0);
+        }
+    }
+
+    #[test]
+    fn test_inline_pool_runs_jobs_synchronously() {
+        let threadpool: ThreadPool = ThreadPool::inline();
+        let order: Arc<Mutex<Vec<&str>>> = Arc::new(Mutex::new(Vec::new()));
+
+        order.lock().unwrap().push("before");
+        let job_order = Arc::clone(&order);
+        threadpool
+            .execute(move || job_order.lock().unwrap().push("job"))
+            .unwrap();
+        order.lock().unwrap().push("after");
+
+        assert_eq!(threadpool.num_threads(), 0);
+        assert_eq!(*order.lock().unwrap(), vec!["before", "job", "after"]);
+    }
+
+    #[test]
+    fn test_running_jobs_lists_named_jobs() {
+        let threadpool: ThreadPool = ThreadPool::new(3);
+        let (started_sender, started) = mpsc::channel();
+        let (release, release_receiver) = mpsc::channel::<()>();
+        let release_receiver = Arc::new(Mutex::new(release_receiver));
+
+        for name in ["resize images", "send emails"] {
+            let started_sender = started_sender.clone();
+            let release_receiver = Arc::clone(&release_receiver);
+            threadpool
+                .execute_named(name, move || {
+                    started_sender.send(()).unwrap();
+                    let _ = release_receiver.lock().unwrap().recv();
+                })
+                .unwrap();
+        }
+        started.recv().unwrap();
+        started.recv().unwrap();
+
+        let mut names: Vec<String> = threadpool
+            .running_jobs()
+            .into_iter()
+            .filter_map(|job| job.job_name)
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["resize images", "send emails"]);
+
+        drop(release);
+        drop(threadpool);
+    }
+
+    #[test]
+    fn test_fast_job_skips_bulk_backlog() {
+        let threadpool: ThreadPool = ThreadPool::builder()
+            .num_threads(3)
+            .reserved_fast_workers(1)
+            .build()
+            .unwrap_or_else(|_| panic!("failed to build pool"));
+
+        for _ in 0..30 {
+            threadpool
+                .execute(|| thread::sleep(Duration::from_millis(20)))
+                .unwrap();
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        let submitted: Instant = Instant::now();
+        threadpool
+            .execute_fast(move || sender.send(Instant::now()).unwrap())
+            .unwrap();
+
+        let started: Instant = receiver.recv().unwrap();
+        assert!(started - submitted < Duration::from_millis(200));
+    }
+
+    #[test]
+    fn test_recycle_worker_reruns_worker_init() {
+        let inits: Arc<Mutex<HashMap<usize, usize>>> = Arc::new(Mutex::new(HashMap::new()));
+        let init_counts = Arc::clone(&inits);
+        let threadpool: ThreadPool = ThreadPool::builder()
+            .num_threads(2)
+            .worker_init(move |id| *init_counts.lock().unwrap().entry(id).or_default() += 1)
+            .build()
+            .unwrap_or_else(|_| panic!("failed to build pool"));
+
+        let handles: Vec<JobHandle<usize>> = (0..20)
+            .map(|i| {
+                threadpool.submit(move || {
+                    thread::sleep(Duration::from_millis(2));
+                    i
+                })
+            })
+            .collect();
+        threadpool.recycle_worker(0);
+
+        let results: Vec<usize> = join_all(handles)
+            .into_iter()
+            .map(|result| result.unwrap())
+            .collect();
+        assert_eq!(results, (0..20).collect::<Vec<usize>>());
+        assert_eq!(threadpool.submit(|| 1).join().unwrap(), 1);
+
+        // The replacement thread runs `worker_init` on its own schedule.
+        while inits.lock().unwrap().get(&0) != Some(&2) {
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(inits.lock().unwrap()[&1], 1);
+    }
+
+    #[test]
+    fn test_prewarm_waits_for_every_worker() {
+        let ready: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+        let init_ready = Arc::clone(&ready);
+
+        let threadpool: ThreadPool = ThreadPool::builder()
+            .num_threads(4)
+            .worker_init(move |_| {
+                thread::sleep(Duration::from_millis(20));
+                init_ready.fetch_add(1, Ordering::SeqCst);
+            })
+            .prewarm(true)
+            .build()
+            .unwrap();
+
+        assert_eq!(ready.load(Ordering::SeqCst), threadpool.num_threads());
+    }
+
+    #[test]
+    fn test_execute_fallible_routes_errors_to_handler() {
+        let errors: Arc<Mutex<Vec<&str>>> = Arc::new(Mutex::new(Vec::new()));
+        let handled = Arc::clone(&errors);
+        let threadpool: ThreadPool = ThreadPool::builder()
+            .num_threads(2)
+            .error_handler(move |err| {
+                handled
+                    .lock()
+                    .unwrap()
+                    .push(*err.downcast::<&str>().unwrap());
+            })
+            .build()
+            .unwrap();
+
+        threadpool
+            .execute_fallible(|| Err::<(), _>("boom"))
+            .unwrap();
+        threadpool.execute_fallible(|| Ok::<_, &str>(1)).unwrap();
+        drop(threadpool);
+
+        assert_eq!(*errors.lock().unwrap(), vec!["boom"]);
+    }
+
+    #[cfg(panic = "unwind")]
+    #[test]
+    fn test_cleanup_runs_after_panicking_job() {
+        let threadpool: ThreadPool = ThreadPool::new(1);
+        let (sender, receiver) = mpsc::channel();
+
+        threadpool
+            .execute_with_cleanup(
+                || panic!("job failed"),
+                move || sender.send("cleaned up").unwrap(),
+            )
+            .unwrap();
+
+        assert_eq!(
+            receiver.recv_timeout(Duration::from_secs(5)),
+            Ok("cleaned up")
+        );
+        while threadpool.metrics().jobs_panicked < 1 {
+            thread::yield_now();
+        }
+    }
+
+    #[test]
+    fn test_wait_for_idle_workers_waits_for_running_jobs() {
+        let threadpool: ThreadPool = ThreadPool::new(2);
+        let (release, released) = mpsc::channel::<()>();
+        let released: Arc<Mutex<mpsc::Receiver<()>>> = Arc::new(Mutex::new(released));
+
+        for _ in 0..2 {
+            let released = Arc::clone(&released);
+            threadpool
+                .execute(move || released.lock().unwrap().recv().unwrap())
+                .unwrap();
+        }
+        while threadpool.metrics().active_jobs < 2 {
+            thread::yield_now();
+        }
+
+        let idle: AtomicBool = AtomicBool::new(false);
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                threadpool.wait_for_idle_workers(5);
+                idle.store(true, Ordering::SeqCst);
+            });
+
+            thread::sleep(Duration::from_millis(20));
+            assert!(!idle.load(Ordering::SeqCst));
+
+            release.send(()).unwrap();
+            release.send(()).unwrap();
+        });
+
+        assert!(idle.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_try_execute_all_rejects_batch_that_does_not_fit() {
+        let threadpool: ThreadPool = ThreadPool::builder()
+            .num_threads(1)
+            .queue_capacity(3)
+            .build()
+            .unwrap();
+        let (release, released) = mpsc::channel::<()>();
+        let ran: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+
+        threadpool
+            .execute(move || released.recv().unwrap())
+            .unwrap();
+        while threadpool.metrics().active_jobs < 1 {
+            thread::yield_now();
+        }
+        threadpool.execute(|| ()).unwrap();
+
+        let batch: Vec<_> = (0..3)
+            .map(|_| {
+                let ran = Arc::clone(&ran);
+                move || {
+                    ran.fetch_add(1, Ordering::SeqCst);
+                }
+            })
+            .collect();
+        let rejected = threadpool.try_execute_all(batch).unwrap_err();
+        assert_eq!(rejected.len(), 3);
+        assert_eq!(threadpool.metrics().queued_jobs, 1);
+
+        let mut rejected = rejected.into_iter();
+        threadpool
+            .try_execute_all(rejected.by_ref().take(2))
+            .unwrap_or_else(|_| panic!("two jobs fit"));
+        assert_eq!(
+            threadpool.execute(rejected.next().unwrap()),
+            Err(PoolError::QueueFull)
+        );
+
+        release.send(()).unwrap();
+        drop(threadpool);
+        assert_eq!(ran.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_concurrent_try_execute_all_respects_channel_capacity() {
+        let threadpool: ThreadPool = ThreadPool::builder()
+            .num_threads(2)
+            .dequeue_strategy(DequeueStrategy::PerWorkerChannel)
+            .queue_capacity(10)
+            .build()
+            .unwrap();
+        let (release, released) = mpsc::channel::<()>();
+        let released: Arc<Mutex<mpsc::Receiver<()>>> = Arc::new(Mutex::new(released));
+        for _ in 0..2 {
+            let released = Arc::clone(&released);
+            threadpool
+                .execute(move || {
+                    let _ = released.lock().unwrap().recv();
+                })
+                .unwrap();
+        }
+        while threadpool.metrics().active_jobs < 2 {
+            thread::yield_now();
+        }
+
+        let accepted: AtomicUsize = AtomicUsize::new(0);
+        thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    let batch: Vec<fn()> = vec![|| (); 3];
+                    if threadpool.try_execute_all(batch).is_ok() {
+                        accepted.fetch_add(1, Ordering::SeqCst);
+                    }
+                });
+            }
+        });
+
+        // Three batches of three fit in a capacity of ten, a fourth doesn't.
+        assert_eq!(accepted.load(Ordering::SeqCst), 3);
+        assert_eq!(threadpool.metrics().queued_jobs, 9);
+        drop(release);
+    }
+
+    #[test]
+    fn test_try_execute_all_returns_batch_once_every_channel_is_gone() {
+        let threadpool: ThreadPool = ThreadPool::builder()
+            .num_threads(2)
+            .dequeue_strategy(DequeueStrategy::PerWorkerChannel)
+            .catch_panics(false)
+            .respawn_workers(false)
+            .build()
+            .unwrap();
+        let ran: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+        let batch = |count: usize| -> Vec<_> {
+            (0..count)
+                .map(|_| {
+                    let ran = Arc::clone(&ran);
+                    move || {
+                        ran.fetch_add(1, Ordering::SeqCst);
+                    }
+                })
+                .collect()
+        };
+        let kill_worker = |worker_id: u64, alive: usize| {
+            threadpool
+                .execute_with_affinity(worker_id, || panic!("kills a worker"))
+                .unwrap();
+            while threadpool.shared.alive_workers.load(Ordering::SeqCst) > alive {
+                thread::sleep(Duration::from_millis(1));
+            }
+        };
+
+        // The jobs dealt to a dead worker go to the one that's left.
+        kill_worker(1, 1);
+        assert!(threadpool.try_execute_all(batch(4)).is_ok());
+        threadpool.wait_for_idle_workers(1);
+        assert_eq!(ran.load(Ordering::SeqCst), 4);
+
+        kill_worker(0, 0);
+        let submitted: usize = threadpool.metrics().jobs_submitted;
+        let returned = threadpool
+            .try_execute_all(batch(3))
+            .expect_err("no worker is left");
+
+        assert_eq!(returned.len(), 3);
+        assert_eq!(threadpool.metrics().jobs_submitted, submitted);
+        returned.into_iter().for_each(|job| job());
+        assert_eq!(ran.load(Ordering::SeqCst), 7);
+    }
+
+    /// Set for the child process of [`test_quiet_pool_prints_nothing`].
+    const QUIET_CHILD: &str = "COPPER_LINE_QUIET_CHILD";
+
+    #[test]
+    fn test_quiet_pool_child() {
+        if std::env::var_os(QUIET_CHILD).is_none() {
+            return;
+        }
+
+        let threadpool: ThreadPool = ThreadPool::new(4);
+        for _ in 0..10_000 {
+            threadpool.execute(|| ()).unwrap();
+        }
+        drop(threadpool);
+
+        println!("quiet pool done");
+    }
+
+    #[test]
+    fn test_quiet_pool_prints_nothing() {
+        // Re-runs this test binary in a child process so everything the pool writes to the real
+        // stdout can be inspected.
+        let output = std::process::Command::new(std::env::current_exe().unwrap())
+            .args(["--exact", "test::test_quiet_pool_child", "--nocapture"])
+            .env(QUIET_CHILD, "1")
+            .output()
+            .unwrap();
+        let stdout: String = String::from_utf8(output.stdout).unwrap();
+
+        assert!(output.status.success());
+        assert!(stdout.contains("quiet pool done"));
+        assert!(!stdout.contains("Worker"), "{stdout}");
+        assert!(!stdout.contains("Shutting down"), "{stdout}");
+    }
+
+    #[test]
+    fn test_remove_workers_keeps_the_pool_running() {
+        let mut threadpool: ThreadPool = ThreadPool::new(5);
+
+        assert_eq!(threadpool.remove_workers(3), 3);
+        assert_eq!(threadpool.num_threads(), 2);
+        let results: Vec<usize> = join_all((0..20).map(|i| threadpool.submit(move || i)).collect())
+            .into_iter()
+            .map(|result| result.unwrap())
+            .collect();
+        assert_eq!(results, (0..20).collect::<Vec<usize>>());
+
+        assert_eq!(threadpool.remove_workers(5), 1);
+        assert_eq!(threadpool.num_threads(), 1);
+        assert_eq!(threadpool.submit(|| 7).join().unwrap(), 7);
+    }
+
+    #[test]
+    fn test_wait_with_progress_reports_increasing_counts() {
+        let threadpool: ThreadPool = ThreadPool::new(4);
+        for _ in 0..100 {
+            threadpool
+                .execute(|| thread::sleep(Duration::from_micros(200)))
+                .unwrap();
+        }
+
+        let reports: Mutex<Vec<(usize, usize)>> = Mutex::new(Vec::new());
+        threadpool.wait_with_progress(100, |completed, total| {
+            reports.lock().unwrap().push((completed, total));
+        });
+
+        let reports: Vec<(usize, usize)> = reports.into_inner().unwrap();
+        assert!(
+            reports.windows(2).all(|pair| pair[0].0 < pair[1].0),
+            "{reports:?}"
+        );
+        assert!(reports.iter().all(|&(_, total)| total == 100));
+        assert_eq!(reports.last(), Some(&(100, 100)));
+        assert_eq!(threadpool.metrics().jobs_completed, 100);
+    }
+
+    #[test]
+    fn test_run_pending_runs_jobs_in_queue_order() {
+        let threadpool: ThreadPool = ThreadPool::builder().deterministic(true).build().unwrap();
+        let order: Arc<Mutex<Vec<&str>>> = Arc::new(Mutex::new(Vec::new()));
+        let record = |name: &'static str| {
+            let order = Arc::clone(&order);
+            move || order.lock().unwrap().push(name)
+        };
+
+        threadpool.execute(record("first")).unwrap();
+        threadpool.execute(record("second")).unwrap();
+        threadpool.execute_front(record("front")).unwrap();
+        threadpool.execute_fast(record("fast")).unwrap();
+        let requeue = record("requeued");
+        let shared: Arc<Shared> = Arc::clone(&threadpool.shared);
+        threadpool
+            .execute(move || {
+                shared.enqueue(Job::new(requeue), Lane::Back).unwrap();
+            })
+            .unwrap();
+
+        assert_eq!(threadpool.num_threads(), 0);
+        assert!(order.lock().unwrap().is_empty());
+        assert_eq!(threadpool.metrics().queued_jobs, 5);
+        // Neither waits for jobs that no worker will run.
+        threadpool.wait_for_idle_workers(1);
+        assert!(panic::catch_unwind(AssertUnwindSafe(|| {
+            threadpool.wait_with_progress(1, |_, _| ());
+        }))
+        .is_err());
+
+        threadpool.run_pending();
+
+        assert_eq!(
+            *order.lock().unwrap(),
+            vec!["fast", "front", "first", "second", "requeued"]
+        );
+        assert_eq!(threadpool.metrics().jobs_completed, 6);
+        threadpool.wait_with_progress(6, |_, _| ());
+    }
+
+    #[test]
+    fn test_queued_bytes_budget_is_respected() {
+        fn sized_job<const N: usize>() -> impl FnOnce() + Send + 'static {
+            let payload: [u8; N] = [0; N];
+            move || {
+                let _payload: [u8; N] = payload;
+            }
+        }
+
+        let threadpool: ThreadPool = ThreadPool::builder()
+            .num_threads(1)
+            .max_queued_bytes(512)
+            .build()
+            .unwrap();
+        let (release, gate) = mpsc::channel::<()>();
+        threadpool
+            .execute(move || {
+                let _ = gate.recv();
+            })
+            .unwrap();
+        while threadpool.metrics().active_jobs == 0 {
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(threadpool.shared.queued_bytes.load(Ordering::SeqCst), 0);
+
+        assert_eq!(threadpool.execute(sized_job::<300>()), Ok(()));
+        assert_eq!(threadpool.execute(sized_job::<100>()), Ok(()));
+        assert_eq!(
+            threadpool.execute(sized_job::<300>()),
+            Err(PoolError::QueueFull)
+        );
+        assert_eq!(threadpool.execute(sized_job::<100>()), Ok(()));
+        assert_eq!(threadpool.shared.queued_bytes.load(Ordering::SeqCst), 500);
+
+        release.send(()).unwrap();
+        threadpool.wait_for_idle_workers(1);
+        assert_eq!(threadpool.shared.queued_bytes.load(Ordering::SeqCst), 0);
+        assert_eq!(threadpool.execute(sized_job::<300>()), Ok(()));
+    }
+
+    #[test]
+    fn test_large_closure_is_rejected() {
+        let threadpool: ThreadPool = ThreadPool::builder()
+            .num_threads(1)
+            .max_job_size(64)
+            .build()
+            .unwrap();
+        let buffer: [u8; 4096] = [1; 4096];
+
+        let result = threadpool.execute(move || {
+            let _sum: u32 = buffer.iter().map(|&byte| u32::from(byte)).sum();
+        });
+
+        assert!(matches!(
+            result,
+            Err(PoolError::JobTooLarge { size, max: 64 }) if size >= 4096
+        ));
+        let buffer: Arc<[u8; 4096]> = Arc::new(buffer);
+        assert_eq!(threadpool.execute(move || drop(buffer)), Ok(()));
+    }
+
+    #[test]
+    fn test_wait_for_idle_workers_under_stress() {
+        let threadpool: ThreadPool = ThreadPool::new(4);
+        let finished: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+
+        for round in 1..=500 {
+            for _ in 0..4 {
+                let finished = Arc::clone(&finished);
+                threadpool
+                    .execute(move || {
+                        finished.fetch_add(1, Ordering::SeqCst);
+                    })
+                    .unwrap();
+            }
+
+            threadpool.wait_for_idle_workers(4);
+            assert_eq!(finished.load(Ordering::SeqCst), round * 4);
+        }
+    }
+
+    #[test]
+    fn test_wait_with_progress_under_stress() {
+        let threadpool: ThreadPool = ThreadPool::new(4);
+
+        for round in 1..=500 {
+            for _ in 0..4 {
+                threadpool.execute(|| {}).unwrap();
+            }
+
+            let last: Mutex<usize> = Mutex::new(0);
+            threadpool.wait_with_progress(round * 4, |completed: usize, _| {
+                *last.lock().unwrap() = completed;
+            });
+            assert_eq!(*last.lock().unwrap(), round * 4);
+            threadpool.wait_for_idle_workers(4);
+        }
+    }
+
+    #[test]
+    fn test_jobs_see_their_own_context() {
+        let threadpool: ThreadPool = ThreadPool::new(4);
+        let (sender, received) = mpsc::channel();
+
+        for i in 0..20 {
+            let sender = sender.clone();
+            threadpool
+                .execute_with_context(format!("trace-{i}"), move |trace_id: &String| {
+                    sender.send((i, trace_id.clone())).unwrap();
+                })
+                .unwrap();
+        }
+        drop(sender);
+
+        let mut seen: Vec<(usize, String)> = received.iter().collect();
+        seen.sort();
+        assert_eq!(
+            seen,
+            (0..20)
+                .map(|i| (i, format!("trace-{i}")))
+                .collect::<Vec<(usize, String)>>()
+        );
+    }
+
+    #[test]
+    fn test_queued_job_names_in_queue_order() {
+        let threadpool: ThreadPool = ThreadPool::new(1);
+        let (started_sender, started) = mpsc::channel();
+        let (release, release_receiver) = mpsc::channel::<()>();
+
+        threadpool
+            .execute_named("blocker", move || {
+                started_sender.send(()).unwrap();
+                let _ = release_receiver.recv();
+            })
+            .unwrap();
+        started.recv().unwrap();
+
+        threadpool.execute_named("parse", || ()).unwrap();
+        threadpool.execute_named("render", || ()).unwrap();
+        threadpool.execute(|| ()).unwrap();
+        threadpool.execute_named("upload", || ()).unwrap();
+
+        assert_eq!(
+            threadpool.queued_job_names(),
+            vec!["parse", "render", "<unnamed>", "upload"]
+        );
+        assert_eq!(threadpool.metrics().queued_jobs, 4);
+
+        drop(release);
+    }
+
+    #[test]
+    fn test_result_observer_sees_every_job() {
+        type Seen = Vec<(Option<String>, bool)>;
+        let seen: Arc<Mutex<Seen>> = Arc::new(Mutex::new(Vec::new()));
+        let observed = Arc::clone(&seen);
+        let threadpool: ThreadPool = ThreadPool::builder()
+            .num_threads(2)
+            .result_observer(move |meta| {
+                observed
+                    .lock()
+                    .unwrap()
+                    .push((meta.job_name.clone(), meta.panicked));
+            })
+            .build()
+            .unwrap();
+
+        threadpool.execute_named("ok", || ()).unwrap();
+        threadpool
+            .execute_named("fails", || panic!("observed"))
+            .unwrap();
+        let _ = threadpool.submit(|| 1).join();
+        let _ = threadpool.submit(|| -> usize { panic!("observed") }).join();
+        drop(threadpool);
+
+        let mut seen = seen.lock().unwrap().clone();
+        seen.sort();
+        assert_eq!(
+            seen,
+            vec![
+                (None, false),
+                (None, true),
+                (Some("fails".to_string()), true),
+                (Some("ok".to_string()), false),
+            ]
+        );
+    }
+
+    /// Shuts down a single-worker pool whose worker is busy with three jobs queued behind it.
+    ///
+    /// # Returns
+    ///
+    /// Whether the busy job finished, and how many of the queued jobs ran.
+    fn shut_down_busy_pool(mode: DrainMode) -> (bool, usize) {
+        let threadpool: ThreadPool = ThreadPool::builder()
+            .num_threads(1)
+            .drain_mode(mode)
+            .build()
+            .unwrap();
+        let blocker_done: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
+        let queued_ran: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+        let (started_sender, started) = mpsc::channel();
+        let (release, release_receiver) = mpsc::channel::<()>();
+
+        let done = Arc::clone(&blocker_done);
+        threadpool
+            .execute(move || {
+                started_sender.send(()).unwrap();
+                let _ = release_receiver.recv();
+                done.store(true, Ordering::SeqCst);
+            })
+            .unwrap();
+        started.recv().unwrap();
+
+        for _ in 0..3 {
+            let queued_ran = Arc::clone(&queued_ran);
+            threadpool
+                .execute(move || {
+                    queued_ran.fetch_add(1, Ordering::SeqCst);
+                })
+                .unwrap();
+        }
+
+        let releaser = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100));
+            drop(release);
+        });
+        threadpool.shutdown();
+        releaser.join().unwrap();
+
+        (
+            blocker_done.load(Ordering::SeqCst),
+            queued_ran.load(Ordering::SeqCst),
+        )
+    }
+
+    #[test]
+    fn test_drain_mode_all_runs_queued_jobs() {
+        assert_eq!(shut_down_busy_pool(DrainMode::All), (true, 3));
+    }
+
+    #[test]
+    fn test_drain_mode_running_only_discards_queued_jobs() {
+        assert_eq!(shut_down_busy_pool(DrainMode::RunningOnly), (true, 0));
+    }
+
+    #[test]
+    fn test_shutdown_report_counts_drained_jobs() {
+        let threadpool: ThreadPool = ThreadPool::builder()
+            .num_threads(1)
+            .drain_mode(DrainMode::RunningOnly)
+            .build()
+            .unwrap();
+        let (started_sender, started) = mpsc::channel();
+        let (release, release_receiver) = mpsc::channel::<()>();
+
+        threadpool
+            .execute(move || {
+                started_sender.send(()).unwrap();
+                let _ = release_receiver.recv();
+            })
+            .unwrap();
+        started.recv().unwrap();
+        for _ in 0..5 {
+            threadpool.execute(|| {}).unwrap();
+        }
+
+        let releaser = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100));
+            drop(release);
+        });
+        let report: ShutdownReport = threadpool.shutdown();
+        releaser.join().unwrap();
+
+        assert_eq!(
+            report,
+            ShutdownReport {
+                completed: 1,
+                drained_unrun: 5,
+                still_running_at_timeout: Vec::new(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_execute_front_jumps_the_queue() {
+        let threadpool: ThreadPool = ThreadPool::new(1);
+        let order: Arc<Mutex<Vec<&str>>> = Arc::new(Mutex::new(Vec::new()));
+        let (started_sender, started) = mpsc::channel();
+        let (release, release_receiver) = mpsc::channel::<()>();
+
+        threadpool
+            .execute(move || {
+                started_sender.send(()).unwrap();
+                let _ = release_receiver.recv();
+            })
+            .unwrap();
+        started.recv().unwrap();
+
+        for name in ["back 1", "back 2"] {
+            let order = Arc::clone(&order);
+            threadpool
+                .execute(move || order.lock().unwrap().push(name))
+                .unwrap();
+        }
+        for name in ["front 1", "front 2", "front 3"] {
+            let order = Arc::clone(&order);
+            threadpool
+                .execute_front(move || order.lock().unwrap().push(name))
+                .unwrap();
+        }
+
+        drop(release);
+        drop(threadpool);
+
+        assert_eq!(
+            *order.lock().unwrap(),
+            vec!["front 3", "front 2", "front 1", "back 1", "back 2"]
+        );
     }
 }
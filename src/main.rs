@@ -11,7 +11,7 @@ use copper_line::{PoolCreationError, ThreadPool};
 fn main() {
     let listener = TcpListener::bind("127.0.0.1:7878").unwrap();
 
-    let pool: ThreadPool = match ThreadPool::build(8) {
+    let pool: ThreadPool = match ThreadPool::builder().num_threads(8).verbose(true).build() {
         Ok(pool) => pool,
         Err(err) => {
             eprintln!("ThreadPool error detected");
@@ -19,6 +19,7 @@ fn main() {
                 PoolCreationError::LessThanOne => {
                     eprintln!("Must initiate a thread count greater than 0.")
                 }
+                err => eprintln!("{err}"),
             }
             std::process::exit(1);
         }
@@ -27,9 +28,11 @@ fn main() {
     for stream in listener.incoming() {
         let stream = stream.unwrap();
 
-        pool.execute(|| {
+        if let Err(err) = pool.execute(|| {
             handle_connection(stream);
-        });
+        }) {
+            eprintln!("Failed to queue connection: {err}");
+        }
     }
 
     println!("Shutting down!");
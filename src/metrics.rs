@@ -0,0 +1,347 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        RwLock,
+    },
+    time::{Duration, Instant},
+};
+
+/// Each new sample moves the average queue wait an eighth of the way towards it.
+const QUEUE_WAIT_SMOOTHING: u64 = 8;
+
+/// Point-in-time snapshot of a pool's counters, returned by
+/// [`ThreadPool::metrics`](crate::ThreadPool::metrics).
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Metrics {
+    /// Jobs handed to the pool.
+    pub jobs_submitted: usize,
+    /// Jobs that finished running, including the ones that panicked.
+    pub jobs_completed: usize,
+    /// Jobs that panicked.
+    pub jobs_panicked: usize,
+    /// Jobs that ran past the [`default_job_timeout`](crate::ThreadPoolBuilder::default_job_timeout).
+    pub jobs_timed_out: usize,
+    /// Largest number of jobs that were waiting in the queue at once.
+    pub peak_queue_depth: usize,
+    /// Jobs currently running on a worker.
+    pub active_jobs: usize,
+    /// Jobs currently waiting for a worker.
+    pub queued_jobs: usize,
+}
+
+/// Metrics read while no job could move between states, returned by
+/// [`ThreadPool::snapshot_consistent`](crate::ThreadPool::snapshot_consistent).
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct PoolSnapshot {
+    /// Satisfies `queued_jobs + active_jobs + jobs_completed == jobs_submitted`, unless the
+    /// metrics were reset while jobs were queued or running, or queued jobs were discarded
+    /// without running by a [`DrainMode::RunningOnly`](crate::DrainMode::RunningOnly) shutdown.
+    /// Discarded jobs stay counted as submitted but leave `queued_jobs`.
+    pub metrics: Metrics,
+    /// When the snapshot was taken. Not serialized, since an [`Instant`] only means something
+    /// inside the process that took it.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub taken_at: Instant,
+}
+
+/// Live counters shared between the pool and its workers.
+#[derive(Default)]
+pub(crate) struct Counters {
+    /// Held shared by every update that moves a job between states, and exclusively by
+    /// [`Counters::consistent_snapshot`].
+    transitions: RwLock<()>,
+    jobs_submitted: AtomicUsize,
+    jobs_completed: AtomicUsize,
+    jobs_panicked: AtomicUsize,
+    jobs_timed_out: AtomicUsize,
+    peak_queue_depth: AtomicUsize,
+    active_jobs: AtomicUsize,
+    queued_jobs: AtomicUsize,
+    /// Jobs discarded without running, see [`Counters::jobs_discarded`].
+    discarded_jobs: AtomicUsize,
+    /// Exponentially weighted average of how long jobs waited before starting, in nanoseconds.
+    /// 0 until a job has started.
+    queue_wait_nanos: AtomicU64,
+}
+
+impl Counters {
+    pub(crate) fn job_submitted(&self) {
+        let _transition = self.transitions.read().unwrap();
+        self.jobs_submitted.fetch_add(1, Ordering::Relaxed);
+        let depth = self.queued_jobs.fetch_add(1, Ordering::Relaxed) + 1;
+        self.peak_queue_depth.fetch_max(depth, Ordering::Relaxed);
+    }
+
+    /// Takes back a [`Counters::job_submitted`] for a job that couldn't be handed to a worker
+    /// after all.
+    pub(crate) fn job_rejected(&self) {
+        let _transition = self.transitions.read().unwrap();
+        self.jobs_submitted.fetch_sub(1, Ordering::Relaxed);
+        self.queued_jobs.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// Removes `count` jobs that were discarded from the queue without running.
+    pub(crate) fn jobs_discarded(&self, count: usize) {
+        let _transition = self.transitions.read().unwrap();
+        self.queued_jobs.fetch_sub(count, Ordering::Relaxed);
+        self.discarded_jobs.fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// Jobs discarded so far plus those still queued, read together so a job being discarded
+    /// meanwhile is counted once.
+    pub(crate) fn unrun_jobs(&self) -> usize {
+        let _quiesced = self.transitions.write().unwrap();
+        self.discarded_jobs.load(Ordering::SeqCst) + self.queued_jobs.load(Ordering::SeqCst)
+    }
+
+    /// Jobs submitted but not started yet.
+    pub(crate) fn queued_jobs(&self) -> usize {
+        self.queued_jobs.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn job_started(&self) {
+        let _transition = self.transitions.read().unwrap();
+        self.queued_jobs.fetch_sub(1, Ordering::Relaxed);
+        self.active_jobs.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn jobs_completed(&self) -> usize {
+        self.jobs_completed.load(Ordering::SeqCst)
+    }
+
+    /// Folds the `wait` of a job that just started into the average queue wait.
+    pub(crate) fn job_waited(&self, wait: Duration) {
+        let sample: u64 = u64::try_from(wait.as_nanos()).unwrap_or(u64::MAX);
+
+        let _ =
+            self.queue_wait_nanos
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |average| {
+                    Some(match average {
+                        // Kept above 0, which means no sample yet.
+                        0 => sample.max(1),
+                        _ => {
+                            average - average / QUEUE_WAIT_SMOOTHING + sample / QUEUE_WAIT_SMOOTHING
+                        }
+                    })
+                });
+    }
+
+    pub(crate) fn avg_queue_wait(&self) -> Duration {
+        Duration::from_nanos(self.queue_wait_nanos.load(Ordering::Relaxed))
+    }
+
+    pub(crate) fn job_finished(&self, panicked: bool) {
+        let _transition = self.transitions.read().unwrap();
+        if panicked {
+            self.jobs_panicked.fetch_add(1, Ordering::Relaxed);
+        }
+        self.jobs_completed.fetch_add(1, Ordering::Relaxed);
+        self.active_jobs.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn job_timed_out(&self) {
+        self.jobs_timed_out.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self) -> Metrics {
+        Metrics {
+            jobs_submitted: self.jobs_submitted.load(Ordering::Relaxed),
+            jobs_completed: self.jobs_completed.load(Ordering::Relaxed),
+            jobs_panicked: self.jobs_panicked.load(Ordering::Relaxed),
+            jobs_timed_out: self.jobs_timed_out.load(Ordering::Relaxed),
+            peak_queue_depth: self.peak_queue_depth.load(Ordering::Relaxed),
+            active_jobs: self.active_jobs.load(Ordering::Relaxed),
+            queued_jobs: self.queued_jobs.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Like [`Counters::snapshot`], but waits out any update in progress and holds off new ones
+    /// while reading, so the counters agree with each other.
+    pub(crate) fn consistent_snapshot(&self) -> PoolSnapshot {
+        let _quiesced = self.transitions.write().unwrap();
+
+        PoolSnapshot {
+            metrics: self.snapshot(),
+            taken_at: Instant::now(),
+        }
+    }
+
+    /// Zeroes the cumulative counters. The active and queued gauges are live state and are left
+    /// alone.
+    pub(crate) fn reset(&self) {
+        // Exclusive, so a consistent snapshot never sees half of the counters zeroed.
+        let _quiesced = self.transitions.write().unwrap();
+        self.jobs_submitted.store(0, Ordering::Relaxed);
+        self.jobs_completed.store(0, Ordering::Relaxed);
+        self.jobs_panicked.store(0, Ordering::Relaxed);
+        self.jobs_timed_out.store(0, Ordering::Relaxed);
+        self.peak_queue_depth.store(0, Ordering::Relaxed);
+        self.discarded_jobs.store(0, Ordering::Relaxed);
+        self.queue_wait_nanos.store(0, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            mpsc,
+        },
+        thread,
+        time::Duration,
+    };
+
+    use crate::{join_all, JobHandle, Metrics, PoolSnapshot, ThreadPool};
+
+    fn wait_for_completed(threadpool: &ThreadPool, count: usize) -> Metrics {
+        loop {
+            let metrics: Metrics = threadpool.metrics();
+            if metrics.jobs_completed >= count {
+                return metrics;
+            }
+            std::thread::yield_now();
+        }
+    }
+
+    #[test]
+    fn test_reset_metrics_only_counts_new_jobs() {
+        let threadpool: ThreadPool = ThreadPool::new(1);
+        let (release, released) = mpsc::channel::<()>();
+
+        // The first batch piles up behind a blocked worker.
+        threadpool
+            .execute(move || {
+                let _ = released.recv();
+            })
+            .unwrap();
+        let first: Vec<JobHandle<()>> = (0..5).map(|_| threadpool.submit(|| ())).collect();
+        threadpool.execute(|| panic!("first batch")).unwrap();
+        assert!(threadpool.metrics().peak_queue_depth >= 6);
+        drop(release);
+        join_all(first);
+        wait_for_completed(&threadpool, 7);
+
+        threadpool.reset_metrics();
+
+        // One job at a time, so the queue never gets deeper than 1.
+        for _ in 0..3 {
+            threadpool.submit(|| ()).join().unwrap();
+        }
+        let _ = threadpool.submit(|| panic!("second batch")).join();
+        let metrics: Metrics = wait_for_completed(&threadpool, 4);
+
+        assert_eq!(metrics.jobs_submitted, 4);
+        assert_eq!(metrics.jobs_completed, 4);
+        assert_eq!(metrics.jobs_panicked, 1);
+        assert_eq!(metrics.peak_queue_depth, 1);
+    }
+
+    #[test]
+    fn test_avg_queue_latency_grows_on_saturated_pool() {
+        let threadpool: ThreadPool = ThreadPool::new(1);
+        assert_eq!(threadpool.avg_queue_latency(), Duration::ZERO);
+
+        // Each job waits for the ones before it, 5ms apiece.
+        join_all(
+            (0..10)
+                .map(|_| threadpool.submit(|| thread::sleep(Duration::from_millis(5))))
+                .collect(),
+        );
+        let latency: Duration = threadpool.avg_queue_latency();
+
+        assert!(latency >= Duration::from_millis(5), "{latency:?}");
+        assert!(latency < Duration::from_secs(1), "{latency:?}");
+    }
+
+    #[test]
+    fn test_consistent_snapshot_adds_up_while_jobs_flow() {
+        let threadpool: ThreadPool = ThreadPool::new(4);
+        let stop: AtomicBool = AtomicBool::new(false);
+
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                while !stop.load(Ordering::SeqCst) {
+                    threadpool.execute(|| ()).unwrap();
+                }
+            });
+
+            for _ in 0..1_000 {
+                let snapshot: PoolSnapshot = threadpool.snapshot_consistent();
+                let metrics: Metrics = snapshot.metrics;
+
+                assert_eq!(
+                    metrics.queued_jobs + metrics.active_jobs + metrics.jobs_completed,
+                    metrics.jobs_submitted,
+                    "{metrics:?}"
+                );
+            }
+            stop.store(true, Ordering::SeqCst);
+        });
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_pool_state_serializes_to_json() {
+        use crate::{JobTiming, RunningJob, ShutdownReport};
+
+        let threadpool: ThreadPool = ThreadPool::new(1);
+        let id = threadpool.execute_tracked(|| ()).unwrap();
+        let metrics: Metrics = wait_for_completed(&threadpool, 1);
+
+        let json: serde_json::Value = serde_json::to_value(&metrics).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "jobs_submitted": 1,
+                "jobs_completed": 1,
+                "jobs_panicked": 0,
+                "jobs_timed_out": 0,
+                "peak_queue_depth": 1,
+                "active_jobs": 0,
+                "queued_jobs": 0,
+            })
+        );
+
+        let snapshot: PoolSnapshot = threadpool.snapshot_consistent();
+        let json: serde_json::Value = serde_json::to_value(&snapshot).unwrap();
+        assert_eq!(json["metrics"]["jobs_completed"], 1);
+        assert!(json.get("taken_at").is_none(), "{json}");
+
+        let timing: JobTiming = threadpool.timing(id).unwrap();
+        let json: serde_json::Value = serde_json::to_value(timing).unwrap();
+        assert!(json["queued_for"].is_object(), "{json}");
+        assert!(json["ran_for"].is_object(), "{json}");
+
+        let (release, released) = mpsc::channel::<()>();
+        threadpool
+            .execute_named("export", move || {
+                let _ = released.recv();
+            })
+            .unwrap();
+        let running: RunningJob = loop {
+            if let Some(running) = threadpool.running_jobs().pop() {
+                break running;
+            }
+            thread::yield_now();
+        };
+        let json: serde_json::Value = serde_json::to_value(&running).unwrap();
+        assert_eq!(json["worker_id"], 0);
+        assert_eq!(json["job_name"], "export");
+        assert!(json["running_for"].is_object(), "{json}");
+        drop(release);
+
+        let report: ShutdownReport = threadpool.shutdown();
+        let json: serde_json::Value = serde_json::to_value(&report).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "completed": 2,
+                "drained_unrun": 0,
+                "still_running_at_timeout": [],
+            })
+        );
+    }
+}
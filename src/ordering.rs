@@ -0,0 +1,195 @@
+use std::{
+    collections::BTreeSet,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Condvar, Mutex, MutexGuard,
+    },
+};
+
+/// Makes jobs submitted with [`ThreadPool::execute_ordered`](crate::ThreadPool::execute_ordered)
+/// start in the order they were submitted.
+#[derive(Default)]
+pub(crate) struct StartOrder {
+    next_ticket: AtomicU64,
+    /// Held from taking a ticket until its job is queued, so jobs are queued in ticket order.
+    /// Otherwise a worker could wait for the turn of a job queued behind its own.
+    submitting: Mutex<()>,
+    state: Mutex<OrderState>,
+    /// Signalled whenever `next` moves on.
+    turn: Condvar,
+}
+
+#[derive(Default)]
+struct OrderState {
+    /// Ticket whose job may start next.
+    next: u64,
+    /// Tickets above `next` that were already released, out of turn, by jobs that were dropped
+    /// without running.
+    released: BTreeSet<u64>,
+}
+
+/// A job's place in the [`StartOrder`]. Dropping it lets the next job start.
+pub(crate) struct Ticket {
+    order: Arc<StartOrder>,
+    number: u64,
+}
+
+impl StartOrder {
+    /// Keeps other threads from taking tickets until the guard is dropped.
+    pub(crate) fn lock_submissions(&self) -> MutexGuard<'_, ()> {
+        self.submitting.lock().unwrap()
+    }
+
+    /// Hands out the next place in line.
+    pub(crate) fn ticket(self: &Arc<StartOrder>) -> Ticket {
+        Ticket {
+            order: Arc::clone(self),
+            number: self.next_ticket.fetch_add(1, Ordering::SeqCst),
+        }
+    }
+}
+
+impl Ticket {
+    /// Blocks until every job with a lower ticket has started or been dropped.
+    pub(crate) fn wait_turn(&self) {
+        let state = self.order.state.lock().unwrap();
+
+        drop(
+            self.order
+                .turn
+                .wait_while(state, |state| state.next != self.number)
+                .unwrap(),
+        );
+    }
+}
+
+impl Drop for Ticket {
+    /// Moves the line on, past any later tickets already released by dropped jobs. A ticket
+    /// released out of turn is remembered so the line can skip it.
+    fn drop(&mut self) {
+        let mut state = self.order.state.lock().unwrap();
+
+        if state.next != self.number {
+            state.released.insert(self.number);
+            return;
+        }
+
+        state.next += 1;
+        loop {
+            let next: u64 = state.next;
+            if !state.released.remove(&next) {
+                break;
+            }
+            state.next += 1;
+        }
+
+        drop(state);
+        self.order.turn.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        mem,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            mpsc, Arc, Barrier, Mutex,
+        },
+        thread,
+        time::Duration,
+    };
+
+    use crate::ThreadPool;
+
+    use super::{StartOrder, Ticket};
+
+    #[test]
+    fn test_tickets_take_turns_in_order() {
+        let order: Arc<StartOrder> = Arc::default();
+        let mut tickets: Vec<Ticket> = (0..20).map(|_| order.ticket()).collect();
+        let started: Arc<Mutex<Vec<u64>>> = Arc::new(Mutex::new(Vec::new()));
+
+        // A dropped job gives up its turn.
+        drop(tickets.remove(5));
+
+        let threads: Vec<_> = tickets
+            .into_iter()
+            .rev()
+            .map(|ticket| {
+                let started = Arc::clone(&started);
+                thread::spawn(move || {
+                    ticket.wait_turn();
+                    started.lock().unwrap().push(ticket.number);
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        let expected: Vec<u64> = (0..20).filter(|&number| number != 5).collect();
+        assert_eq!(*started.lock().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_ordered_jobs_all_run() {
+        let threadpool: ThreadPool = ThreadPool::new(4);
+        let ran: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+
+        for _ in 0..100 {
+            let ran = Arc::clone(&ran);
+            threadpool
+                .execute_ordered(move || {
+                    ran.fetch_add(1, Ordering::SeqCst);
+                })
+                .unwrap();
+        }
+        threadpool.wait_for_idle_workers(4);
+
+        assert_eq!(ran.load(Ordering::SeqCst), 100);
+    }
+
+    #[test]
+    fn test_concurrent_ordered_jobs_start_in_order_on_one_worker() {
+        let threadpool: ThreadPool = ThreadPool::new(1);
+        let (started_sender, started) = mpsc::channel::<(usize, usize)>();
+        let (release, released) = mpsc::channel::<()>();
+
+        // Everything is queued before the worker takes the first ordered job.
+        threadpool
+            .execute(move || released.recv().unwrap())
+            .unwrap();
+        let submitting: Barrier = Barrier::new(8);
+        thread::scope(|scope| {
+            for submitter in 0..8 {
+                let started_sender = started_sender.clone();
+                let threadpool = &threadpool;
+                let submitting = &submitting;
+                scope.spawn(move || {
+                    submitting.wait();
+                    for job in 0..200 {
+                        let started_sender = started_sender.clone();
+                        threadpool
+                            .execute_ordered(move || started_sender.send((submitter, job)).unwrap())
+                            .unwrap();
+                    }
+                });
+            }
+        });
+        drop(started_sender);
+        release.send(()).unwrap();
+
+        // A job waiting for a turn that's queued behind it would block the only worker.
+        let mut next_job: Vec<usize> = vec![0; 8];
+        for _ in 0..8 * 200 {
+            let Ok((submitter, job)) = started.recv_timeout(Duration::from_secs(10)) else {
+                // The stuck worker can't be joined.
+                mem::forget(threadpool);
+                panic!("ordered jobs deadlocked");
+            };
+            assert_eq!(job, next_job[submitter]);
+            next_job[submitter] += 1;
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use std::time::Duration;
+
+/// The OS id of the calling thread.
+pub(crate) fn current_id() -> u64 {
+    os::current_thread_id()
+}
+
+/// OS-level details of a worker's thread, recorded by the thread itself as it starts.
+pub(crate) struct WorkerThread {
+    pub(crate) os_id: u64,
+    cpu_clock: os::CpuClock,
+}
+
+impl WorkerThread {
+    pub(crate) fn current() -> WorkerThread {
+        WorkerThread {
+            os_id: current_id(),
+            cpu_clock: os::CpuClock::current(),
+        }
+    }
+
+    /// Reads how much CPU time the thread has used so far. Only call it while the thread is known
+    /// to be running, e.g. while holding the lock of the map the thread removes itself from
+    /// before it exits: once the thread is gone its clock id may name another thread.
+    ///
+    /// # Returns
+    ///
+    /// The CPU time, or [`None`] where the OS doesn't report it per thread.
+    pub(crate) fn cpu_time(&self) -> Option<Duration> {
+        self.cpu_clock.read()
+    }
+}
+
+// `time_t` is a `long` on every 64-bit Linux target, but not on all 32-bit ones.
+#[cfg(all(target_os = "linux", target_pointer_width = "64"))]
+mod cpu_clock {
+    use std::{
+        ffi::{c_int, c_long, c_ulong},
+        time::Duration,
+    };
+
+    #[repr(C)]
+    struct Timespec {
+        tv_sec: c_long,
+        tv_nsec: c_long,
+    }
+
+    extern "C" {
+        fn pthread_self() -> c_ulong;
+        fn pthread_getcpuclockid(thread: c_ulong, clock_id: *mut c_int) -> c_int;
+        fn clock_gettime(clock_id: c_int, time: *mut Timespec) -> c_int;
+    }
+
+    /// Clock counting the CPU time of one thread, readable from any thread while that one runs.
+    pub(crate) struct CpuClock(Option<c_int>);
+
+    impl CpuClock {
+        pub(crate) fn current() -> CpuClock {
+            let mut clock_id: c_int = 0;
+            // SAFETY: `pthread_self` is always a valid thread, and `clock_id` is valid for writes.
+            let result: c_int = unsafe { pthread_getcpuclockid(pthread_self(), &mut clock_id) };
+
+            CpuClock((result == 0).then_some(clock_id))
+        }
+
+        /// Only call it while the clock's thread is running, see [`super::WorkerThread::cpu_time`].
+        pub(crate) fn read(&self) -> Option<Duration> {
+            let mut time: Timespec = Timespec {
+                tv_sec: 0,
+                tv_nsec: 0,
+            };
+            // SAFETY: `time` is valid for writes and matches `struct timespec` on this target. The
+            // clock id is a plain number, and the caller keeps its thread running meanwhile.
+            if unsafe { clock_gettime(self.0?, &mut time) } != 0 {
+                return None;
+            }
+
+            Some(Duration::new(time.tv_sec as u64, time.tv_nsec as u32))
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod os {
+    pub(super) use super::cpu_clock::CpuClock;
+    use std::ffi::c_int;
+
+    extern "C" {
+        fn gettid() -> c_int;
+    }
+
+    /// The kernel's thread id, as shown by `ps -L`, `top -H` and `perf`.
+    pub(super) fn current_thread_id() -> u64 {
+        // SAFETY: `gettid` has no preconditions and always succeeds.
+        let tid: c_int = unsafe { gettid() };
+        tid as u64
+    }
+}
+
+#[cfg(not(all(target_os = "linux", target_pointer_width = "64")))]
+mod cpu_clock {
+    use std::time::Duration;
+
+    /// Per-thread CPU time isn't read on this platform.
+    pub(crate) struct CpuClock;
+
+    impl CpuClock {
+        pub(crate) fn current() -> CpuClock {
+            CpuClock
+        }
+
+        pub(crate) fn read(&self) -> Option<Duration> {
+            None
+        }
+    }
+}
+
+#[cfg(windows)]
+mod os {
+    pub(super) use super::cpu_clock::CpuClock;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetCurrentThreadId() -> u32;
+    }
+
+    pub(super) fn current_thread_id() -> u64 {
+        // SAFETY: `GetCurrentThreadId` has no preconditions and always succeeds.
+        u64::from(unsafe { GetCurrentThreadId() })
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+mod os {
+    pub(super) use super::cpu_clock::CpuClock;
+    use std::{
+        collections::hash_map::DefaultHasher,
+        hash::{Hash, Hasher},
+        thread,
+    };
+
+    /// There's no portable way to get an OS id, so this falls back to a hash of the
+    /// [`ThreadId`](thread::ThreadId), which is unique but unknown to the OS.
+    pub(super) fn current_thread_id() -> u64 {
+        let mut hasher: DefaultHasher = DefaultHasher::new();
+        thread::current().id().hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use crate::ThreadPool;
+
+    #[cfg(all(target_os = "linux", target_pointer_width = "64"))]
+    #[test]
+    fn test_busy_worker_reports_more_cpu_time() {
+        use std::{
+            hint,
+            sync::mpsc,
+            thread,
+            time::{Duration, Instant},
+        };
+
+        let threadpool: ThreadPool = ThreadPool::builder()
+            .num_threads(2)
+            .prewarm(true)
+            .build()
+            .unwrap();
+        let (ran_on, os_ids) = mpsc::channel();
+        let spinner = ran_on.clone();
+
+        threadpool
+            .execute(move || {
+                spinner.send(("spin", super::current_id())).unwrap();
+                let started: Instant = Instant::now();
+                while started.elapsed() < Duration::from_millis(200) {
+                    hint::black_box(started);
+                }
+            })
+            .unwrap();
+        threadpool
+            .execute(move || {
+                ran_on.send(("sleep", super::current_id())).unwrap();
+                thread::sleep(Duration::from_millis(200));
+            })
+            .unwrap();
+        threadpool.wait_for_idle_workers(2);
+
+        let worker_of = |os_id: u64| {
+            threadpool
+                .os_thread_ids()
+                .into_iter()
+                .find(|&(_, id)| id == os_id)
+                .unwrap()
+                .0
+        };
+        let cpu_times = threadpool.worker_cpu_times();
+        let os_ids: Vec<(&str, u64)> = os_ids.try_iter().collect();
+        let cpu_time_of = |job: &str| {
+            let &(_, os_id) = os_ids.iter().find(|&&(name, _)| name == job).unwrap();
+            cpu_times[worker_of(os_id)].1
+        };
+        let spin: Duration = cpu_time_of("spin");
+        let sleep: Duration = cpu_time_of("sleep");
+
+        assert!(spin >= Duration::from_millis(150), "{spin:?}");
+        assert!(sleep < Duration::from_millis(50), "{sleep:?}");
+    }
+
+    #[test]
+    fn test_os_thread_ids_are_distinct_per_worker() {
+        let threadpool: ThreadPool = ThreadPool::builder()
+            .num_threads(4)
+            .prewarm(true)
+            .build()
+            .unwrap();
+
+        let ids: Vec<(usize, u64)> = threadpool.os_thread_ids();
+        let os_ids: HashSet<u64> = ids.iter().map(|&(_, os_id)| os_id).collect();
+
+        assert_eq!(
+            ids.iter()
+                .map(|&(worker_id, _)| worker_id)
+                .collect::<Vec<usize>>(),
+            vec![0, 1, 2, 3]
+        );
+        assert_eq!(os_ids.len(), 4);
+        assert!(!os_ids.contains(&super::current_id()));
+    }
+}
@@ -0,0 +1,84 @@
+use std::{collections::VecDeque, panic, sync::Arc};
+
+use crate::{JobHandle, ThreadPool};
+
+/// Jobs kept in flight per worker by [`ThreadPool::par_map_iter`].
+pub(crate) const PREFETCH_PER_THREAD: usize = 2;
+
+/// Iterator returned by [`ThreadPool::par_map_iter`].
+pub(crate) struct ParMapIter<'a, I, F, R> {
+    threadpool: &'a ThreadPool,
+    items: I,
+    f: Arc<F>,
+    /// Submitted jobs, oldest first.
+    in_flight: VecDeque<JobHandle<R>>,
+    max_in_flight: usize,
+}
+
+impl<'a, I, F, R> ParMapIter<'a, I, F, R>
+where
+    I: Iterator,
+{
+    pub(crate) fn new(threadpool: &'a ThreadPool, items: I, f: F) -> ParMapIter<'a, I, F, R> {
+        ParMapIter {
+            threadpool,
+            items,
+            f: Arc::new(f),
+            in_flight: VecDeque::new(),
+            max_in_flight: threadpool.num_threads().max(1) * PREFETCH_PER_THREAD,
+        }
+    }
+}
+
+impl<I, F, R> Iterator for ParMapIter<'_, I, F, R>
+where
+    I: Iterator,
+    I::Item: Send + 'static,
+    F: Fn(I::Item) -> R + Send + Sync + 'static,
+    R: Send + 'static,
+{
+    type Item = R;
+
+    /// Tops the in-flight jobs back up from `items`, then waits for the oldest one.
+    ///
+    /// # Panics
+    ///
+    /// Re-raises the panic of a job whose `f` panicked.
+    fn next(&mut self) -> Option<R> {
+        while self.in_flight.len() < self.max_in_flight {
+            let Some(item) = self.items.next() else {
+                break;
+            };
+
+            let f: Arc<F> = Arc::clone(&self.f);
+            self.in_flight
+                .push_back(self.threadpool.submit(move || f(item)));
+        }
+
+        let handle: JobHandle<R> = self.in_flight.pop_front()?;
+        match handle.join() {
+            Ok(result) => Some(result),
+            Err(err) => panic::resume_unwind(err.into_panic()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ThreadPool;
+
+    use super::PREFETCH_PER_THREAD;
+
+    #[test]
+    fn test_par_map_iter_bounds_in_flight_jobs() {
+        let threadpool: ThreadPool = ThreadPool::new(2);
+        let max_in_flight: usize = 2 * PREFETCH_PER_THREAD;
+
+        let mut results = threadpool.par_map_iter(0..10_000u64, |i| i * 2);
+        assert_eq!(results.next(), Some(0));
+        assert!(threadpool.metrics().jobs_submitted <= max_in_flight);
+
+        let rest: Vec<u64> = results.collect();
+        assert_eq!(rest, (1..10_000u64).map(|i| i * 2).collect::<Vec<u64>>());
+    }
+}
@@ -0,0 +1,175 @@
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use crate::{
+    producer::{InFlight, Permit},
+    PoolError, ResultSender, ThreadPool,
+};
+
+/// Feeds the output of jobs on one pool into jobs on another.
+///
+/// Only a limited number of items are in flight between the first job being queued and the
+/// second finishing, so neither pool's queue grows with the size of the input.
+pub struct Pipeline<'a> {
+    stage1: &'a ThreadPool,
+    stage2: &'a ThreadPool,
+    capacity: usize,
+}
+
+impl<'a> Pipeline<'a> {
+    /// Creates a new Pipeline.
+    ///
+    /// * `stage1` - Pool that runs the first stage.
+    ///
+    /// * `stage2` - Pool that runs the second stage.
+    ///
+    /// At most twice as many items as `stage2` has workers are in flight at a time.
+    pub fn new(stage1: &'a ThreadPool, stage2: &'a ThreadPool) -> Pipeline<'a> {
+        Pipeline {
+            stage1,
+            stage2,
+            capacity: stage2.num_threads().max(1) * 2,
+        }
+    }
+
+    /// Runs `f1` on every item in the first stage, then `f2` on each of its results in the
+    /// second.
+    ///
+    /// * `items` - Inputs to the first stage. The calling thread blocks before taking the next
+    ///   one while the pipeline is full.
+    ///
+    /// * `f1` - First stage, run on `stage1`.
+    ///
+    /// * `f2` - Second stage, run on `stage2`.
+    ///
+    /// # Returns
+    ///
+    /// The output of `f2` for each item, in the order of `items`, or the [`PoolError`] of the
+    /// first job either pool rejected. No more items are taken once a job has been rejected.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if a job of either stage panicked, as that item has no output.
+    pub fn process<A, B, C, F1, F2>(
+        &self,
+        items: impl IntoIterator<Item = A>,
+        f1: F1,
+        f2: F2,
+    ) -> Result<Vec<C>, PoolError>
+    where
+        A: Send + 'static,
+        B: Send + 'static,
+        C: Send + 'static,
+        F1: Fn(A) -> B + Send + Sync + 'static,
+        F2: Fn(B) -> C + Send + Sync + 'static,
+    {
+        let f1: Arc<F1> = Arc::new(f1);
+        let in_flight: Arc<InFlight> = Arc::default();
+        let rejection: Mutex<Option<PoolError>> = Mutex::new(None);
+
+        let (handoff, stage1_results) = ResultSender::channel(None);
+        let (sender, stage2_results) = ResultSender::channel(None);
+
+        let count: usize = thread::scope(|scope| {
+            let rejection: &Mutex<Option<PoolError>> = &rejection;
+            let stage2: &ThreadPool = self.stage2;
+            let f2: Arc<F2> = Arc::new(f2);
+
+            // Hands stage-1 results over to stage 2 while the calling thread keeps feeding
+            // stage 1.
+            scope.spawn(move || {
+                for (index, intermediate, permit) in stage1_results {
+                    if rejection.lock().unwrap().is_some() {
+                        continue;
+                    }
+
+                    let f2: Arc<F2> = Arc::clone(&f2);
+                    let job = move || {
+                        let _permit: Permit = permit;
+                        (index, f2(intermediate))
+                    };
+                    if let Err(err) = stage2.execute_into(job, &sender) {
+                        rejection.lock().unwrap().get_or_insert(err);
+                    }
+                }
+            });
+
+            let mut count: usize = 0;
+            for (index, item) in items.into_iter().enumerate() {
+                // Released once the item's stage-2 job is done, or as soon as it's dropped.
+                let permit: Permit = in_flight.acquire(self.capacity);
+                if rejection.lock().unwrap().is_some() {
+                    break;
+                }
+
+                let f1: Arc<F1> = Arc::clone(&f1);
+                let job = move || (index, f1(item), permit);
+                if let Err(err) = self.stage1.execute_into(job, &handoff) {
+                    rejection.lock().unwrap().get_or_insert(err);
+                    break;
+                }
+                count += 1;
+            }
+            drop(handoff);
+
+            count
+        });
+
+        let mut outputs: Vec<Option<C>> = (0..count).map(|_| None).collect();
+        for (index, output) in stage2_results {
+            outputs[index] = Some(output);
+        }
+
+        if let Some(err) = rejection.into_inner().unwrap() {
+            return Err(err);
+        }
+        Ok(outputs
+            .into_iter()
+            .map(|output| output.expect("pipeline job panicked"))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Pipeline, PoolError, ThreadPool};
+
+    #[test]
+    fn test_pipeline_parses_then_squares() {
+        let parsers: ThreadPool = ThreadPool::new(2);
+        let squarers: ThreadPool = ThreadPool::new(1);
+        let pipeline: Pipeline = Pipeline::new(&parsers, &squarers);
+        let inputs: Vec<String> = (1..=200).map(|i| i.to_string()).collect();
+
+        let squares: Vec<u64> = pipeline
+            .process(
+                inputs,
+                |input: String| input.parse::<u64>().unwrap(),
+                |value: u64| value * value,
+            )
+            .unwrap();
+
+        assert_eq!(squares, (1..=200).map(|i| i * i).collect::<Vec<u64>>());
+        // At most two items are in flight for a single stage-2 worker.
+        assert!(parsers.metrics().peak_queue_depth <= 2);
+        assert!(squarers.metrics().peak_queue_depth <= 2);
+    }
+
+    #[test]
+    fn test_pipeline_reports_rejected_jobs() {
+        let parsers: ThreadPool = ThreadPool::new(2);
+        let squarers: ThreadPool = ThreadPool::builder()
+            .num_threads(1)
+            .max_job_size(1)
+            .build()
+            .unwrap();
+        let pipeline: Pipeline = Pipeline::new(&parsers, &squarers);
+
+        let result = pipeline.process(0..100u64, |value| value + 1, |value| value * value);
+
+        assert!(matches!(result, Err(PoolError::JobTooLarge { max: 1, .. })));
+        assert!(parsers.metrics().jobs_submitted < 100);
+    }
+}
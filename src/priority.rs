@@ -0,0 +1,138 @@
+use std::io;
+
+/// OS scheduling priority for worker threads, see
+/// [`ThreadPoolBuilder::thread_priority`](crate::ThreadPoolBuilder::thread_priority).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ThreadPriority {
+    /// Below normal, so workers yield to foreground threads.
+    Low,
+    /// Whatever the OS gives new threads.
+    #[default]
+    Normal,
+    /// Above normal. Usually needs elevated privileges.
+    High,
+}
+
+/// Applies `priority` to the calling thread.
+///
+/// # Returns
+///
+/// The OS error if the priority couldn't be set. [`ThreadPriority::Normal`] is a no-op.
+pub(crate) fn apply(priority: ThreadPriority) -> io::Result<()> {
+    match priority {
+        ThreadPriority::Normal => Ok(()),
+        ThreadPriority::Low | ThreadPriority::High => os::set_current_thread_priority(priority),
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod os {
+    use std::{
+        ffi::{c_int, c_uint},
+        io,
+    };
+
+    use super::ThreadPriority;
+
+    const PRIO_PROCESS: c_int = 0;
+
+    extern "C" {
+        fn setpriority(which: c_int, who: c_uint, prio: c_int) -> c_int;
+    }
+
+    /// Linux keeps a nice value per thread, so `who = 0` only affects the calling thread.
+    pub(super) fn set_current_thread_priority(priority: ThreadPriority) -> io::Result<()> {
+        let nice: c_int = match priority {
+            ThreadPriority::Low => 10,
+            ThreadPriority::Normal => 0,
+            ThreadPriority::High => -10,
+        };
+
+        // SAFETY: `setpriority` has no memory-safety preconditions.
+        if unsafe { setpriority(PRIO_PROCESS, 0, nice) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(windows)]
+mod os {
+    use std::{
+        ffi::{c_int, c_void},
+        io,
+    };
+
+    use super::ThreadPriority;
+
+    const THREAD_PRIORITY_BELOW_NORMAL: c_int = -1;
+    const THREAD_PRIORITY_NORMAL: c_int = 0;
+    const THREAD_PRIORITY_ABOVE_NORMAL: c_int = 1;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetCurrentThread() -> *mut c_void;
+        fn SetThreadPriority(thread: *mut c_void, priority: c_int) -> i32;
+    }
+
+    pub(super) fn set_current_thread_priority(priority: ThreadPriority) -> io::Result<()> {
+        let level: c_int = match priority {
+            ThreadPriority::Low => THREAD_PRIORITY_BELOW_NORMAL,
+            ThreadPriority::Normal => THREAD_PRIORITY_NORMAL,
+            ThreadPriority::High => THREAD_PRIORITY_ABOVE_NORMAL,
+        };
+
+        // SAFETY: `GetCurrentThread` returns a pseudo-handle that is always valid for the calling
+        // thread.
+        if unsafe { SetThreadPriority(GetCurrentThread(), level) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+mod os {
+    use std::io;
+
+    use super::ThreadPriority;
+
+    /// Other Unixes apply `setpriority` to the whole process, so per-thread priorities aren't
+    /// supported there.
+    pub(super) fn set_current_thread_priority(_priority: ThreadPriority) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "per-thread priorities are not supported on this platform",
+        ))
+    }
+}
+
+#[cfg(all(test, any(target_os = "linux", windows)))]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    use crate::{join_all, PoolEvent, ThreadPool, ThreadPriority};
+
+    #[test]
+    fn test_low_priority_workers_still_run_jobs() {
+        let events: Arc<Mutex<Vec<PoolEvent>>> = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&events);
+        let threadpool: ThreadPool = ThreadPool::builder()
+            .num_threads(2)
+            .thread_priority(ThreadPriority::Low)
+            .on_event(move |event| recorded.lock().unwrap().push(event.clone()))
+            .prewarm(true)
+            .build()
+            .unwrap();
+
+        let results: Vec<usize> = join_all((0..4).map(|i| threadpool.submit(move || i)).collect())
+            .into_iter()
+            .map(|result| result.unwrap())
+            .collect();
+
+        assert_eq!(results, vec![0, 1, 2, 3]);
+        assert!(events.lock().unwrap().is_empty());
+    }
+}
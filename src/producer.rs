@@ -0,0 +1,149 @@
+use std::{
+    sync::{Arc, Condvar, Mutex, Weak},
+    thread::{self, JoinHandle},
+};
+
+use crate::{queue::Lane, Shared};
+
+/// Handle to a producer started with [`ThreadPool::run_producer`](crate::ThreadPool::run_producer).
+pub struct ProducerHandle {
+    thread: JoinHandle<()>,
+}
+
+/// Number of items handed to the pool whose `consume` job hasn't finished yet.
+///
+/// Also bounds the items between the stages of a [`Pipeline`](crate::Pipeline).
+#[derive(Default)]
+pub(crate) struct InFlight {
+    count: Mutex<usize>,
+    changed: Condvar,
+}
+
+/// Counts one item as in flight until dropped, even if its job never runs.
+pub(crate) struct Permit(Arc<InFlight>);
+
+impl ProducerHandle {
+    /// Blocks until the producer has run out of items and every item has been consumed.
+    ///
+    /// # Returns
+    ///
+    /// [`Err`] with the panic payload if `produce` panicked. Panics in `consume` are handled by
+    /// the pool like those of any other job.
+    pub fn join(self) -> thread::Result<()> {
+        self.thread.join()
+    }
+}
+
+impl InFlight {
+    /// Blocks until fewer than `bound` items are in flight, then counts one more.
+    pub(crate) fn acquire(self: &Arc<InFlight>, bound: usize) -> Permit {
+        let count = self.count.lock().unwrap();
+        let mut count = self
+            .changed
+            .wait_while(count, |count| *count >= bound)
+            .unwrap();
+
+        *count += 1;
+        Permit(Arc::clone(self))
+    }
+
+    fn wait_until_empty(&self) {
+        let count = self.count.lock().unwrap();
+        drop(self.changed.wait_while(count, |count| *count > 0).unwrap());
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        *self.0.count.lock().unwrap() -= 1;
+        self.0.changed.notify_all();
+    }
+}
+
+/// Starts a thread that submits a `consume` job to `shared` for every item `produce` returns,
+/// with at most `bound` items in flight.
+pub(crate) fn spawn<T, P, F>(
+    shared: Weak<Shared>,
+    bound: usize,
+    mut produce: P,
+    consume: F,
+) -> ProducerHandle
+where
+    T: Send + 'static,
+    P: FnMut() -> Option<T> + Send + 'static,
+    F: Fn(T) + Send + Sync + 'static,
+{
+    let consume: Arc<F> = Arc::new(consume);
+    let in_flight: Arc<InFlight> = Arc::default();
+
+    let thread: JoinHandle<()> = thread::spawn(move || {
+        while let Some(item) = produce() {
+            // Waits without holding on to the pool, so one that is being dropped can go away and
+            // release the permits of the jobs it never ran.
+            let permit: Permit = in_flight.acquire(bound);
+            let Some(shared) = shared.upgrade() else {
+                break;
+            };
+
+            let consume: Arc<F> = Arc::clone(&consume);
+            let job = shared.job(move || {
+                let _permit: Permit = permit;
+                consume(item);
+            });
+            if shared.enqueue(job, Lane::Back).is_err() {
+                break;
+            }
+        }
+
+        in_flight.wait_until_empty();
+    });
+
+    ProducerHandle { thread }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use crate::ThreadPool;
+
+    #[test]
+    fn test_producer_items_are_consumed_once_with_bounded_queue() {
+        let threadpool: ThreadPool = ThreadPool::new(2);
+        let consumed: Arc<Vec<AtomicUsize>> =
+            Arc::new((0..1000).map(|_| AtomicUsize::new(0)).collect());
+        let done: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+        let most_in_flight: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+        let mut next: usize = 0;
+
+        let producer = {
+            let consumed = Arc::clone(&consumed);
+            let finished = Arc::clone(&done);
+            let done = Arc::clone(&done);
+            let most_in_flight = Arc::clone(&most_in_flight);
+            threadpool.run_producer(
+                move || {
+                    // Every item before `next` has been handed to the pool.
+                    most_in_flight
+                        .fetch_max(next - finished.load(Ordering::SeqCst), Ordering::SeqCst);
+                    next += 1;
+                    (next <= 1000).then_some(next - 1)
+                },
+                move |item: usize| {
+                    consumed[item].fetch_add(1, Ordering::SeqCst);
+                    done.fetch_add(1, Ordering::SeqCst);
+                },
+            )
+        };
+        producer.join().unwrap();
+
+        assert!(consumed
+            .iter()
+            .all(|count| count.load(Ordering::SeqCst) == 1));
+        assert_eq!(done.load(Ordering::SeqCst), 1000);
+        assert!(most_in_flight.load(Ordering::SeqCst) <= 4);
+    }
+}
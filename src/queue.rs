@@ -0,0 +1,417 @@
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Condvar, Mutex,
+    },
+};
+
+use crate::{job::Job, metrics::Counters};
+
+/// What happens to queued jobs when a [`ThreadPool`](crate::ThreadPool) shuts down.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DrainMode {
+    /// Workers keep taking jobs until the queue is empty.
+    #[default]
+    All,
+    /// Running jobs finish, but every job still waiting in the queue is discarded.
+    RunningOnly,
+}
+
+/// Storage for the jobs waiting in a pool's queue, set with
+/// [`ThreadPoolBuilder::queue_backend`](crate::ThreadPoolBuilder::queue_backend).
+///
+/// The pool does the blocking and waking: it only calls a backend while holding its own queue
+/// lock, so an implementation needs interior mutability but sees no contention. Jobs submitted
+/// with [`ThreadPool::execute_fast`](crate::ThreadPool::execute_fast) bypass the backend.
+pub trait QueueBackend: Send + Sync {
+    /// Stores a newly submitted job.
+    fn push(&self, job: Job);
+
+    /// Stores a job submitted with [`ThreadPool::execute_front`](crate::ThreadPool::execute_front).
+    /// Defaults to [`QueueBackend::push`].
+    fn push_front(&self, job: Job) {
+        self.push(job);
+    }
+
+    /// Takes the job a worker should run next. Must return a job whenever [`QueueBackend::len`]
+    /// is nonzero.
+    fn pop(&self) -> Option<Job>;
+
+    /// Number of stored jobs.
+    fn len(&self) -> usize;
+
+    /// Whether no jobs are stored.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Calls `visitor` with each stored job in the order they'll be popped, for
+    /// [`ThreadPool::queued_job_names`](crate::ThreadPool::queued_job_names). Backends that don't
+    /// override it list nothing.
+    fn visit(&self, _visitor: &mut dyn FnMut(&Job)) {}
+}
+
+/// The default [`QueueBackend`]: jobs run in the order they were submitted.
+#[derive(Default)]
+pub struct FifoQueue {
+    jobs: Mutex<VecDeque<Job>>,
+}
+
+impl FifoQueue {
+    /// Creates an empty FifoQueue.
+    pub fn new() -> FifoQueue {
+        FifoQueue::default()
+    }
+}
+
+impl QueueBackend for FifoQueue {
+    fn push(&self, job: Job) {
+        self.jobs.lock().unwrap().push_back(job);
+    }
+
+    fn push_front(&self, job: Job) {
+        self.jobs.lock().unwrap().push_front(job);
+    }
+
+    fn pop(&self) -> Option<Job> {
+        self.jobs.lock().unwrap().pop_front()
+    }
+
+    fn len(&self) -> usize {
+        self.jobs.lock().unwrap().len()
+    }
+
+    fn visit(&self, visitor: &mut dyn FnMut(&Job)) {
+        self.jobs.lock().unwrap().iter().for_each(visitor);
+    }
+}
+
+/// Where in a [`JobQueue`] a new job goes.
+pub(crate) enum Lane {
+    Back,
+    Front,
+    Fast,
+}
+
+/// FIFO of pending jobs that workers block on.
+///
+/// Jobs in the fast lane are taken before anything else. Workers reserved for the fast lane only
+/// ever take fast jobs, so a flood of bulk jobs can't hold them up.
+pub(crate) struct JobQueue {
+    state: Mutex<QueueState>,
+    /// Most jobs that may be queued across both lanes, if bounded.
+    capacity: Option<usize>,
+    /// Signalled for every new job. Waited on by general workers.
+    available: Condvar,
+    /// Signalled for new fast-lane jobs. Waited on by reserved workers.
+    fast_available: Condvar,
+    /// Signalled whenever a worker starts waiting for a job.
+    worker_idle: Condvar,
+}
+
+struct QueueState {
+    jobs: Box<dyn QueueBackend>,
+    fast: VecDeque<Job>,
+    closed: bool,
+    /// Set on a [`DrainMode::RunningOnly`] shutdown so workers stop taking jobs.
+    stop_dequeue: bool,
+    /// Workers blocked in [`JobQueue::pop`] because there was nothing for them to take.
+    idle_workers: usize,
+}
+
+impl QueueState {
+    fn has_room_for(&self, count: usize, capacity: Option<usize>) -> bool {
+        capacity.is_none_or(|capacity| self.jobs.len() + self.fast.len() + count <= capacity)
+    }
+}
+
+impl JobQueue {
+    /// Creates a new JobQueue.
+    ///
+    /// * `capacity` - Most jobs that may be queued, or [`None`] for no limit.
+    ///
+    /// * `backend` - Storage for the regular lane.
+    pub(crate) fn new(capacity: Option<usize>, backend: Box<dyn QueueBackend>) -> JobQueue {
+        JobQueue {
+            capacity,
+            state: Mutex::new(QueueState {
+                jobs: backend,
+                fast: VecDeque::new(),
+                closed: false,
+                stop_dequeue: false,
+                idle_workers: 0,
+            }),
+            available: Condvar::new(),
+            fast_available: Condvar::new(),
+            worker_idle: Condvar::new(),
+        }
+    }
+
+    /// Most jobs that may be queued, if bounded.
+    pub(crate) fn capacity(&self) -> Option<usize> {
+        self.capacity
+    }
+
+    /// Queues `job` in `lane` and counts it as submitted in `counters`.
+    ///
+    /// The job is counted while the lock is held, so a worker can't start it before it's counted.
+    ///
+    /// # Returns
+    ///
+    /// [`Err`] with the job if the queue is full.
+    pub(crate) fn push(&self, job: Job, lane: Lane, counters: &Counters) -> Result<(), Job> {
+        let mut state = self.state.lock().unwrap();
+        if !state.has_room_for(1, self.capacity) {
+            return Err(job);
+        }
+        counters.job_submitted();
+
+        match lane {
+            Lane::Back => state.jobs.push(job),
+            Lane::Front => state.jobs.push_front(job),
+            Lane::Fast => {
+                state.fast.push_back(job);
+                // Whichever worker wakes first takes it; the other goes back to waiting.
+                self.fast_available.notify_one();
+            }
+        }
+
+        drop(state);
+        self.available.notify_one();
+        Ok(())
+    }
+
+    /// Queues every job in `jobs` at the back, or none of them if they don't all fit.
+    ///
+    /// * `wrap` - Turns each closure into the [`Job`] that gets queued.
+    ///
+    /// # Returns
+    ///
+    /// [`Err`] with the jobs, untouched, if the queue doesn't have room for all of them.
+    pub(crate) fn push_all<F>(
+        &self,
+        jobs: Vec<F>,
+        wrap: impl Fn(F) -> Job,
+        counters: &Counters,
+    ) -> Result<(), Vec<F>> {
+        let state = self.state.lock().unwrap();
+        if !state.has_room_for(jobs.len(), self.capacity) {
+            return Err(jobs);
+        }
+
+        for job in jobs {
+            counters.job_submitted();
+            state.jobs.push(wrap(job));
+        }
+
+        drop(state);
+        self.available.notify_all();
+        Ok(())
+    }
+
+    /// Blocks until a job is available.
+    ///
+    /// * `fast_only` - Whether the caller is reserved for the fast lane.
+    ///
+    /// * `retiring` - Flag that makes the caller give up waiting, checked whenever it's woken.
+    ///
+    /// # Returns
+    ///
+    /// The next job, or [`None`] once `retiring` is set or the queue is closed and every job the
+    /// caller may take has been taken.
+    pub(crate) fn pop(&self, fast_only: bool, retiring: &AtomicBool) -> Option<Job> {
+        let mut state = self.state.lock().unwrap();
+        let retiring = || retiring.load(Ordering::SeqCst);
+        let available: &Condvar = if fast_only {
+            &self.fast_available
+        } else {
+            &self.available
+        };
+        let must_wait = |state: &QueueState| {
+            let empty: bool = state.fast.is_empty() && (fast_only || state.jobs.is_empty());
+            empty && !state.closed && !retiring()
+        };
+
+        if must_wait(&state) {
+            state.idle_workers += 1;
+            self.worker_idle.notify_all();
+
+            // Rechecked after every wakeup, since a wakeup can be spurious.
+            while must_wait(&state) {
+                state = available.wait(state).unwrap();
+            }
+            state.idle_workers -= 1;
+        }
+
+        if retiring() || state.stop_dequeue {
+            return None;
+        }
+        if fast_only {
+            return state.fast.pop_front();
+        }
+        state.fast.pop_front().or_else(|| state.jobs.pop())
+    }
+
+    /// # Returns
+    ///
+    /// The job [`JobQueue::pop`] would hand out next, or [`None`] straight away if there isn't
+    /// one.
+    pub(crate) fn try_pop(&self) -> Option<Job> {
+        let mut state = self.state.lock().unwrap();
+        if state.stop_dequeue {
+            return None;
+        }
+
+        state.fast.pop_front().or_else(|| state.jobs.pop())
+    }
+
+    /// Counts a worker that waits for jobs outside the queue as idle, until
+    /// [`JobQueue::leave_idle`].
+    pub(crate) fn enter_idle(&self) {
+        self.state.lock().unwrap().idle_workers += 1;
+        self.worker_idle.notify_all();
+    }
+
+    pub(crate) fn leave_idle(&self) {
+        self.state.lock().unwrap().idle_workers -= 1;
+    }
+
+    /// Blocks until no job is waiting to be started and at least `n` workers are idle.
+    ///
+    /// * `counters` - Also counts the jobs waiting outside the queue, in per-worker channels.
+    pub(crate) fn wait_for_idle(&self, n: usize, counters: &Counters) {
+        let state = self.state.lock().unwrap();
+
+        // Waiting workers stay counted until they wake, so a job that was just pushed may not
+        // have been taken yet.
+        drop(
+            self.worker_idle
+                .wait_while(state, |state| {
+                    state.idle_workers < n
+                        || !state.fast.is_empty()
+                        || !state.jobs.is_empty()
+                        || counters.queued_jobs() > 0
+                })
+                .unwrap(),
+        );
+    }
+
+    /// # Returns
+    ///
+    /// The names of the queued jobs in the order workers will take them, with `<unnamed>` for
+    /// jobs that weren't given one.
+    pub(crate) fn names(&self) -> Vec<String> {
+        let state = self.state.lock().unwrap();
+
+        let mut names: Vec<String> = Vec::new();
+        let mut push_name = |job: &Job| names.push(job.name().unwrap_or("<unnamed>").to_string());
+
+        state.fast.iter().for_each(&mut push_name);
+        state.jobs.visit(&mut push_name);
+        names
+    }
+
+    /// Wakes every waiting worker so they recheck their exit conditions.
+    pub(crate) fn wake_all(&self) {
+        // Taking the lock orders the wakeup after any flag the caller just set.
+        drop(self.state.lock().unwrap());
+        self.available.notify_all();
+        self.fast_available.notify_all();
+    }
+
+    /// Removes every queued job without closing the queue.
+    ///
+    /// # Returns
+    ///
+    /// The jobs in the order workers would have taken them.
+    pub(crate) fn take_all(&self) -> Vec<Job> {
+        let mut state = self.state.lock().unwrap();
+        let mut jobs: Vec<Job> = state.fast.drain(..).collect();
+
+        while let Some(job) = state.jobs.pop() {
+            jobs.push(job);
+        }
+        jobs
+    }
+
+    /// Wakes every worker so they drain the remaining jobs, as far as `mode` allows, and exit.
+    ///
+    /// # Returns
+    ///
+    /// Number of queued jobs discarded.
+    pub(crate) fn close(&self, mode: DrainMode) -> usize {
+        let mut state = self.state.lock().unwrap();
+        state.closed = true;
+
+        let mut discarded: Vec<Job> = Vec::new();
+        if mode == DrainMode::RunningOnly {
+            state.stop_dequeue = true;
+            discarded.extend(state.fast.drain(..));
+            while let Some(job) = state.jobs.pop() {
+                discarded.push(job);
+            }
+        }
+
+        drop(state);
+        self.available.notify_all();
+        self.fast_available.notify_all();
+
+        // Dropped outside the lock, since dropping a job can fail a handle whose continuation
+        // queues another job.
+        discarded.len()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{mpsc, Mutex};
+
+    use crate::{Job, QueueBackend, ThreadPool};
+
+    /// Runs the most recently queued job first.
+    #[derive(Default)]
+    struct StackQueue {
+        jobs: Mutex<Vec<Job>>,
+    }
+
+    impl QueueBackend for StackQueue {
+        fn push(&self, job: Job) {
+            self.jobs.lock().unwrap().push(job);
+        }
+
+        fn pop(&self) -> Option<Job> {
+            self.jobs.lock().unwrap().pop()
+        }
+
+        fn len(&self) -> usize {
+            self.jobs.lock().unwrap().len()
+        }
+    }
+
+    #[test]
+    fn test_custom_queue_backend_orders_jobs() {
+        let threadpool: ThreadPool = ThreadPool::builder()
+            .num_threads(1)
+            .queue_backend(StackQueue::default())
+            .build()
+            .unwrap();
+        let (release, released) = mpsc::channel::<()>();
+        let (sender, order) = mpsc::channel();
+
+        threadpool
+            .execute(move || released.recv().unwrap())
+            .unwrap();
+        while threadpool.metrics().active_jobs < 1 {
+            std::thread::yield_now();
+        }
+        for i in 0..3 {
+            let sender = sender.clone();
+            threadpool.execute(move || sender.send(i).unwrap()).unwrap();
+        }
+        drop(sender);
+
+        release.send(()).unwrap();
+        assert_eq!(order.iter().collect::<Vec<usize>>(), vec![2, 1, 0]);
+    }
+}
@@ -0,0 +1,162 @@
+use std::{
+    mem::{self, MaybeUninit},
+    ptr,
+    sync::{Arc, Mutex},
+};
+
+use crate::job::Task;
+
+/// Largest closure, in bytes, that fits in a recycled allocation.
+const SLOT_SIZE: usize = 64;
+
+/// Most spare allocations kept around for reuse. Any beyond that are freed.
+const MAX_SPARE_SLOTS: usize = 1024;
+
+/// Storage for one small closure, aligned for anything up to 16 bytes.
+#[repr(C, align(16))]
+struct Slot(MaybeUninit<[u8; SLOT_SIZE]>);
+
+/// Hands out allocations for small job closures and takes them back once the job has run, see
+/// [`ThreadPoolBuilder::recycle_job_boxes`](crate::ThreadPoolBuilder::recycle_job_boxes).
+#[derive(Default)]
+pub(crate) struct BoxPool {
+    /// The boxes themselves are what gets reused, so they stay boxed.
+    #[allow(clippy::vec_box)]
+    spare: Mutex<Vec<Box<Slot>>>,
+}
+
+/// A closure stored in a recycled [`Slot`].
+pub(crate) struct RecycledTask {
+    /// [`None`] once the closure has been moved out to run.
+    slot: Option<Box<Slot>>,
+    /// Moves the closure out of the slot, gives the slot back and runs the closure.
+    call: unsafe fn(Box<Slot>, &BoxPool),
+    /// Drops a closure that never ran.
+    drop_closure: unsafe fn(&mut Slot),
+    pool: Arc<BoxPool>,
+}
+
+impl BoxPool {
+    /// Stores `f` in a recycled allocation if it fits, or in a regular [`Box`] otherwise.
+    pub(crate) fn task<F>(self: &Arc<BoxPool>, f: F) -> Task
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        if mem::size_of::<F>() > SLOT_SIZE || mem::align_of::<F>() > mem::align_of::<Slot>() {
+            return Task::Boxed(Box::new(f));
+        }
+
+        let mut slot: Box<Slot> = self
+            .spare
+            .lock()
+            .unwrap()
+            .pop()
+            .unwrap_or_else(|| Box::new(Slot(MaybeUninit::uninit())));
+        // SAFETY: `F` fits in the slot and needs no stricter alignment, checked above.
+        unsafe { ptr::write(slot.0.as_mut_ptr().cast::<F>(), f) };
+
+        Task::Recycled(RecycledTask {
+            slot: Some(slot),
+            call: call::<F>,
+            drop_closure: drop_closure::<F>,
+            pool: Arc::clone(self),
+        })
+    }
+
+    fn give_back(&self, slot: Box<Slot>) {
+        let mut spare = self.spare.lock().unwrap();
+        if spare.len() < MAX_SPARE_SLOTS {
+            spare.push(slot);
+        }
+    }
+}
+
+impl RecycledTask {
+    pub(crate) fn run(mut self) {
+        let slot: Box<Slot> = self.slot.take().unwrap();
+        // SAFETY: `call` was instantiated for the closure written into `slot`, which is read out
+        // exactly once since the slot was taken.
+        unsafe { (self.call)(slot, &self.pool) }
+    }
+}
+
+impl Drop for RecycledTask {
+    fn drop(&mut self) {
+        if let Some(mut slot) = self.slot.take() {
+            // SAFETY: the slot still holds the closure `drop_closure` was instantiated for.
+            unsafe { (self.drop_closure)(&mut slot) };
+            self.pool.give_back(slot);
+        }
+    }
+}
+
+/// # Safety
+///
+/// `slot` must hold an `F` written by [`BoxPool::task`].
+unsafe fn call<F: FnOnce()>(slot: Box<Slot>, pool: &BoxPool) {
+    let f: F = ptr::read(slot.0.as_ptr().cast::<F>());
+    // The slot no longer owns the closure, so it can be reused while the job runs.
+    pool.give_back(slot);
+    f();
+}
+
+/// # Safety
+///
+/// `slot` must hold an `F` written by [`BoxPool::task`].
+unsafe fn drop_closure<F>(slot: &mut Slot) {
+    ptr::drop_in_place(slot.0.as_mut_ptr().cast::<F>());
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use crate::ThreadPool;
+
+    #[test]
+    fn test_recycled_jobs_run_and_drop_their_captures() {
+        let threadpool: ThreadPool = ThreadPool::builder()
+            .num_threads(2)
+            .recycle_job_boxes(true)
+            .build()
+            .unwrap();
+
+        let small: Vec<usize> = (0..50)
+            .map(|i| threadpool.submit(move || i + 1))
+            .map(|handle| handle.join().unwrap())
+            .collect();
+        let buffer: [u64; 32] = [3; 32];
+        let large: u64 = threadpool
+            .submit(move || buffer.iter().sum::<u64>())
+            .join()
+            .unwrap();
+
+        assert_eq!(small, (1..51).collect::<Vec<usize>>());
+        assert_eq!(large, 96);
+
+        let pending: ThreadPool = ThreadPool::builder()
+            .deterministic(true)
+            .recycle_job_boxes(true)
+            .build()
+            .unwrap();
+        let captured: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+        for _ in 0..2 {
+            let captured = Arc::clone(&captured);
+            pending
+                .execute(move || {
+                    captured.fetch_add(1, Ordering::Relaxed);
+                })
+                .unwrap();
+        }
+        pending.run_pending();
+        let never_run = Arc::clone(&captured);
+        pending.execute(move || drop(never_run)).unwrap();
+        drop(pending);
+
+        assert_eq!(captured.load(Ordering::Relaxed), 2);
+        assert_eq!(Arc::strong_count(&captured), 1);
+    }
+}
@@ -0,0 +1,117 @@
+use std::sync::mpsc;
+
+/// Sending half of a result channel created by
+/// [`ThreadPool::result_channel`](crate::ThreadPool::result_channel).
+///
+/// When the pool was built with a [`result_buffer`](crate::ThreadPoolBuilder::result_buffer) the
+/// channel is bounded, and workers block on delivery until the consumer catches up.
+pub struct ResultSender<T> {
+    inner: Inner<T>,
+}
+
+enum Inner<T> {
+    Unbounded(mpsc::Sender<T>),
+    Bounded(mpsc::SyncSender<T>),
+}
+
+impl<T> ResultSender<T> {
+    /// Creates a new result channel.
+    ///
+    /// * `buffer` - Capacity of the channel, or [`None`] for an unbounded one.
+    pub(crate) fn channel(buffer: Option<usize>) -> (ResultSender<T>, mpsc::Receiver<T>) {
+        match buffer {
+            Some(capacity) => {
+                let (sender, receiver) = mpsc::sync_channel(capacity);
+                (
+                    ResultSender {
+                        inner: Inner::Bounded(sender),
+                    },
+                    receiver,
+                )
+            }
+            None => {
+                let (sender, receiver) = mpsc::channel();
+                (
+                    ResultSender {
+                        inner: Inner::Unbounded(sender),
+                    },
+                    receiver,
+                )
+            }
+        }
+    }
+
+    /// Sends `value` to the receiver, blocking while a bounded channel is full.
+    pub fn send(&self, value: T) -> Result<(), mpsc::SendError<T>> {
+        match &self.inner {
+            Inner::Unbounded(sender) => sender.send(value),
+            Inner::Bounded(sender) => sender.send(value),
+        }
+    }
+}
+
+impl<T> Clone for ResultSender<T> {
+    fn clone(&self) -> ResultSender<T> {
+        let inner = match &self.inner {
+            Inner::Unbounded(sender) => Inner::Unbounded(sender.clone()),
+            Inner::Bounded(sender) => Inner::Bounded(sender.clone()),
+        };
+
+        ResultSender { inner }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        thread,
+        time::Duration,
+    };
+
+    use crate::ThreadPool;
+
+    #[test]
+    fn test_result_buffer_throttles_workers() {
+        let threadpool: ThreadPool = ThreadPool::builder()
+            .num_threads(2)
+            .result_buffer(2)
+            .build()
+            .unwrap_or_else(|_| panic!("failed to build pool"));
+        let (sender, receiver) = threadpool.result_channel();
+        let outstanding: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+        let peak: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+
+        for i in 0..20 {
+            let outstanding = Arc::clone(&outstanding);
+            let peak = Arc::clone(&peak);
+
+            threadpool
+                .execute_into(
+                    move || {
+                        let current = outstanding.fetch_add(1, Ordering::SeqCst) + 1;
+                        peak.fetch_max(current, Ordering::SeqCst);
+                        i
+                    },
+                    &sender,
+                )
+                .unwrap();
+        }
+        drop(sender);
+
+        let mut received: Vec<usize> = Vec::new();
+        for value in receiver {
+            outstanding.fetch_sub(1, Ordering::SeqCst);
+            received.push(value);
+            thread::sleep(Duration::from_millis(5));
+        }
+
+        received.sort();
+        assert_eq!(received, (0..20).collect::<Vec<usize>>());
+        // Two values buffered, one blocked in each worker, plus one being handed over.
+        assert!(peak.load(Ordering::SeqCst) <= 5);
+    }
+}
@@ -0,0 +1,62 @@
+use std::thread;
+
+/// Upper bound on the thread count returned by [`suggested_threads`].
+const MAX_SUGGESTED_THREADS: usize = 256;
+
+/// Kind of work a pool will mostly run, used by [`suggested_threads`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Workload {
+    /// Jobs keep a core busy for their whole run.
+    CpuBound,
+    /// Jobs spend time blocked on I/O.
+    IoBound {
+        /// Ratio of time spent blocked to time spent computing. A job that waits 90ms for every
+        /// 10ms of work has a blocking factor of 9.
+        blocking_factor: f64,
+    },
+}
+
+/// Suggests a thread count for `workload` on this machine.
+///
+/// * `workload` - The kind of jobs the pool will run.
+///
+/// # Returns
+///
+/// The available parallelism for [`Workload::CpuBound`], or roughly
+/// `cores * (1 + blocking_factor)` for [`Workload::IoBound`], clamped to 256. Always at least 1.
+pub fn suggested_threads(workload: Workload) -> usize {
+    let cores: usize = thread::available_parallelism().map_or(1, |count| count.get());
+
+    match workload {
+        Workload::CpuBound => cores,
+        Workload::IoBound { blocking_factor } => {
+            // `max` also maps a NaN factor to 0.
+            let threads: f64 = cores as f64 * (1.0 + blocking_factor.max(0.0));
+            (threads.round() as usize).clamp(1, MAX_SUGGESTED_THREADS)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::thread;
+
+    use crate::{suggested_threads, Workload};
+
+    #[test]
+    fn test_cpu_bound_matches_available_parallelism() {
+        let cores: usize = thread::available_parallelism().map_or(1, |count| count.get());
+
+        assert_eq!(suggested_threads(Workload::CpuBound), cores);
+    }
+
+    #[test]
+    fn test_io_bound_suggests_more_threads() {
+        let io_bound: usize = suggested_threads(Workload::IoBound {
+            blocking_factor: 3.0,
+        });
+
+        assert!(io_bound > suggested_threads(Workload::CpuBound));
+        assert!(io_bound <= 256);
+    }
+}
@@ -0,0 +1,189 @@
+use std::{
+    any::Any,
+    collections::HashMap,
+    mem,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    thread,
+    time::Instant,
+};
+
+#[cfg(feature = "serde")]
+use std::time::Duration;
+
+/// Identifier assigned to a job submitted with
+/// [`ThreadPool::execute_tracked`](crate::ThreadPool::execute_tracked) or
+/// [`ThreadPool::submit_tracked`](crate::ThreadPool::submit_tracked).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct JobId(u64);
+
+/// When a tracked job was submitted, started and finished.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct JobTiming {
+    pub submitted_at: Instant,
+    /// [`None`] while the job is still queued.
+    pub started_at: Option<Instant>,
+    /// [`None`] until the job has returned or panicked.
+    pub finished_at: Option<Instant>,
+}
+
+/// Instants only mean something inside the process that took them, so a timing is serialized as
+/// how long the job waited in the queue and how long it ran, each [`None`] until known.
+#[cfg(feature = "serde")]
+impl serde::Serialize for JobTiming {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let queued_for: Option<Duration> = self
+            .started_at
+            .map(|started_at| started_at - self.submitted_at);
+        let ran_for: Option<Duration> = self
+            .started_at
+            .zip(self.finished_at)
+            .map(|(started_at, finished_at)| finished_at - started_at);
+
+        let mut timing = serializer.serialize_struct("JobTiming", 2)?;
+        timing.serialize_field("queued_for", &queued_for)?;
+        timing.serialize_field("ran_for", &ran_for)?;
+        timing.end()
+    }
+}
+
+/// Result of a job submitted with [`ThreadPool::submit_tracked`](crate::ThreadPool::submit_tracked),
+/// with the return value's type erased.
+pub(crate) type TrackedResult = (JobId, thread::Result<Box<dyn Any + Send>>);
+
+/// Hands out [`JobId`]s and records the timing of each tracked job.
+#[derive(Default)]
+pub(crate) struct Tracker {
+    next_id: AtomicU64,
+    timings: Mutex<HashMap<JobId, JobTiming>>,
+    /// Results of the finished jobs submitted with
+    /// [`ThreadPool::submit_tracked`](crate::ThreadPool::submit_tracked).
+    results: Mutex<Vec<TrackedResult>>,
+}
+
+impl Tracker {
+    /// Registers a newly submitted job.
+    pub(crate) fn submitted(&self) -> JobId {
+        let id: JobId = JobId(self.next_id.fetch_add(1, Ordering::Relaxed));
+        let timing: JobTiming = JobTiming {
+            submitted_at: Instant::now(),
+            started_at: None,
+            finished_at: None,
+        };
+
+        self.timings.lock().unwrap().insert(id, timing);
+        id
+    }
+
+    pub(crate) fn started(&self, id: JobId) {
+        if let Some(timing) = self.timings.lock().unwrap().get_mut(&id) {
+            timing.started_at = Some(Instant::now());
+        }
+    }
+
+    pub(crate) fn finished(&self, id: JobId) {
+        if let Some(timing) = self.timings.lock().unwrap().get_mut(&id) {
+            timing.finished_at = Some(Instant::now());
+        }
+    }
+
+    pub(crate) fn timing(&self, id: JobId) -> Option<JobTiming> {
+        self.timings.lock().unwrap().get(&id).copied()
+    }
+
+    pub(crate) fn record_result(&self, id: JobId, result: thread::Result<Box<dyn Any + Send>>) {
+        self.results.lock().unwrap().push((id, result));
+    }
+
+    /// # Returns
+    ///
+    /// Every result recorded so far, ordered by [`JobId`].
+    pub(crate) fn take_results(&self) -> Vec<TrackedResult> {
+        let mut results: Vec<TrackedResult> = mem::take(&mut *self.results.lock().unwrap());
+        results.sort_unstable_by_key(|&(id, _)| id);
+        results
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{sync::mpsc, thread, time::Duration};
+
+    use crate::{JobHandle, JobId, JobTiming, JoinError, ThreadPool};
+
+    #[test]
+    fn test_timing_measures_execution_time() {
+        let threadpool: ThreadPool = ThreadPool::new(2);
+
+        let id = threadpool
+            .execute_tracked(|| thread::sleep(Duration::from_millis(50)))
+            .unwrap();
+
+        let timing: JobTiming = loop {
+            let timing: JobTiming = threadpool.timing(id).unwrap();
+            if timing.finished_at.is_some() {
+                break timing;
+            }
+            thread::sleep(Duration::from_millis(5));
+        };
+
+        let started = timing.started_at.unwrap();
+        let execution: Duration = timing.finished_at.unwrap() - started;
+        assert!(started >= timing.submitted_at);
+        assert!(execution >= Duration::from_millis(50));
+        assert!(execution < Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_dropped_pool_cancels_jobs_queued_with_tracked_ones() {
+        let threadpool: ThreadPool = ThreadPool::builder().deterministic(true).build().unwrap();
+
+        threadpool.execute_tracked(|| ()).unwrap();
+        threadpool.submit_tracked(|| 2).unwrap();
+        let handle: JobHandle<u32> = threadpool.submit(|| 1);
+        drop(threadpool);
+
+        // Joined elsewhere, so a pool kept alive by its own jobs fails the test instead of
+        // hanging it.
+        let (sender, joined) = mpsc::channel();
+        thread::spawn(move || sender.send(handle.join()).unwrap());
+        let result = joined.recv_timeout(Duration::from_secs(10));
+        assert!(matches!(result, Ok(Err(JoinError::Cancelled))));
+    }
+
+    #[test]
+    fn test_shutdown_collect_returns_tracked_results() {
+        let threadpool: ThreadPool = ThreadPool::new(3);
+
+        let ids: Vec<JobId> = (0..5u64)
+            .map(|i| {
+                threadpool
+                    .submit_tracked(move || {
+                        if i == 3 {
+                            panic!("job 3 fails");
+                        }
+                        thread::sleep(Duration::from_millis(5));
+                        i * 10
+                    })
+                    .unwrap()
+            })
+            .collect();
+
+        let results = threadpool.shutdown_collect::<u64>();
+
+        assert_eq!(
+            results.iter().map(|(id, _)| *id).collect::<Vec<JobId>>(),
+            ids
+        );
+        for (i, (_, result)) in results.into_iter().enumerate() {
+            match i {
+                3 => assert!(result.is_err()),
+                _ => assert_eq!(result.unwrap(), i as u64 * 10),
+            }
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Condvar, Mutex},
+    thread::{self, JoinHandle},
+};
+
+use crate::worker;
+
+/// A pool that runs jobs of a single closure type `F`.
+///
+/// [`ThreadPool`](crate::ThreadPool) boxes every job so closures of any type can share a queue.
+/// When every job is the same closure, for example one created in a loop, a TypedThreadPool
+/// queues the closures themselves: no allocation and no virtual call per job. It has none of the
+/// scheduling options of [`ThreadPool`](crate::ThreadPool); panicking jobs are contained and
+/// the worker carries on.
+pub struct TypedThreadPool<F>
+where
+    F: FnOnce() + Send + 'static,
+{
+    workers: Vec<JoinHandle<()>>,
+    shared: Arc<TypedShared<F>>,
+}
+
+struct TypedShared<F> {
+    state: Mutex<TypedQueue<F>>,
+    available: Condvar,
+}
+
+struct TypedQueue<F> {
+    jobs: VecDeque<F>,
+    closed: bool,
+}
+
+impl<F> TypedThreadPool<F>
+where
+    F: FnOnce() + Send + 'static,
+{
+    /// Creates a new TypedThreadPool.
+    ///
+    /// * `thread_count` - Number of threads in the pool.
+    ///
+    /// # Panics
+    ///
+    /// This 'new' function will panic if `thread_count` is zero or the OS fails to spawn a thread.
+    pub fn new(thread_count: usize) -> TypedThreadPool<F> {
+        assert!(thread_count > 0);
+
+        let shared: Arc<TypedShared<F>> = Arc::new(TypedShared {
+            state: Mutex::new(TypedQueue {
+                jobs: VecDeque::new(),
+                closed: false,
+            }),
+            available: Condvar::new(),
+        });
+        let workers: Vec<JoinHandle<()>> = (0..thread_count)
+            .map(|_| {
+                let shared: Arc<TypedShared<F>> = Arc::clone(&shared);
+                thread::spawn(move || shared.run())
+            })
+            .collect();
+
+        TypedThreadPool { workers, shared }
+    }
+
+    /// Runs `f` on one of the workers.
+    ///
+    /// * `f` - Closure to run.
+    pub fn execute(&self, f: F) {
+        self.shared.state.lock().unwrap().jobs.push_back(f);
+        self.shared.available.notify_one();
+    }
+
+    /// # Returns
+    ///
+    /// Number of worker threads in the pool.
+    pub fn num_threads(&self) -> usize {
+        self.workers.len()
+    }
+}
+
+impl<F> TypedShared<F>
+where
+    F: FnOnce() + Send + 'static,
+{
+    /// Body of a worker thread. Runs jobs until the queue is closed and empty.
+    fn run(&self) {
+        loop {
+            let state = self.state.lock().unwrap();
+            let mut state = self
+                .available
+                .wait_while(state, |state| state.jobs.is_empty() && !state.closed)
+                .unwrap();
+
+            let Some(job) = state.jobs.pop_front() else {
+                return;
+            };
+            drop(state);
+
+            worker::run_job(job, true);
+        }
+    }
+}
+
+impl<F> Drop for TypedThreadPool<F>
+where
+    F: FnOnce() + Send + 'static,
+{
+    /// Runs every queued job, then joins the workers.
+    fn drop(&mut self) {
+        self.shared.state.lock().unwrap().closed = true;
+        self.shared.available.notify_all();
+
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use crate::TypedThreadPool;
+
+    #[test]
+    fn test_typed_pool_runs_every_job() {
+        let total: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+        let threadpool = TypedThreadPool::new(4);
+
+        // Every job is the same closure type, so they're queued without boxing.
+        for i in 0..100_000 {
+            let total = Arc::clone(&total);
+            threadpool.execute(move || {
+                total.fetch_add(i, Ordering::Relaxed);
+            });
+        }
+        assert_eq!(threadpool.num_threads(), 4);
+        drop(threadpool);
+
+        assert_eq!(total.load(Ordering::Relaxed), (0..100_000).sum::<usize>());
+    }
+}
@@ -0,0 +1,186 @@
+use std::{
+    collections::HashMap,
+    sync::{Condvar, Mutex, Weak},
+    time::{Duration, Instant},
+};
+
+use crate::{CancelToken, PoolEvent, Shared};
+
+/// Cancels jobs that run longer than the pool's
+/// [`default_job_timeout`](crate::ThreadPoolBuilder::default_job_timeout).
+///
+/// One watchdog thread serves the whole pool, sleeping until the earliest deadline.
+pub(crate) struct Watchdog {
+    timeout: Duration,
+    state: Mutex<WatchdogState>,
+    /// Signalled when a deadline is added or the watchdog is stopped.
+    changed: Condvar,
+}
+
+struct WatchdogState {
+    next_id: u64,
+    deadlines: HashMap<u64, Deadline>,
+    stopped: bool,
+}
+
+struct Deadline {
+    at: Instant,
+    token: CancelToken,
+    worker_id: usize,
+    job_name: Option<String>,
+}
+
+impl Watchdog {
+    pub(crate) fn new(timeout: Duration) -> Watchdog {
+        Watchdog {
+            timeout,
+            state: Mutex::new(WatchdogState {
+                next_id: 0,
+                deadlines: HashMap::new(),
+                stopped: false,
+            }),
+            changed: Condvar::new(),
+        }
+    }
+
+    /// Starts the clock on a job that is about to run.
+    ///
+    /// # Returns
+    ///
+    /// The key to pass to [`Watchdog::unwatch`] once the job is done, and the token the job should
+    /// check.
+    pub(crate) fn watch(&self, worker_id: usize, job_name: Option<String>) -> (u64, CancelToken) {
+        let token: CancelToken = CancelToken::new();
+        let mut state = self.state.lock().unwrap();
+        let key: u64 = state.next_id;
+        state.next_id += 1;
+        state.deadlines.insert(
+            key,
+            Deadline {
+                at: Instant::now() + self.timeout,
+                token: token.clone(),
+                worker_id,
+                job_name,
+            },
+        );
+
+        drop(state);
+        self.changed.notify_one();
+        (key, token)
+    }
+
+    /// Stops watching a job that finished, whether or not it timed out.
+    pub(crate) fn unwatch(&self, key: u64) {
+        self.state.lock().unwrap().deadlines.remove(&key);
+    }
+
+    /// Makes [`Watchdog::run`] return.
+    pub(crate) fn stop(&self) {
+        self.state.lock().unwrap().stopped = true;
+        self.changed.notify_one();
+    }
+
+    /// Body of the watchdog thread. Cancels every job whose deadline passed and reports it to
+    /// `shared`, until [`Watchdog::stop`] is called.
+    pub(crate) fn run(&self, shared: Weak<Shared>) {
+        let mut state = self.state.lock().unwrap();
+
+        loop {
+            if state.stopped {
+                return;
+            }
+
+            let now: Instant = Instant::now();
+            let expired: Vec<u64> = state
+                .deadlines
+                .iter()
+                .filter(|(_, deadline)| deadline.at <= now)
+                .map(|(key, _)| *key)
+                .collect();
+
+            if !expired.is_empty() {
+                let expired: Vec<Deadline> = expired
+                    .iter()
+                    .filter_map(|key| state.deadlines.remove(key))
+                    .collect();
+                drop(state);
+
+                // Reported outside the lock, since the event callback may take its time.
+                for deadline in expired {
+                    deadline.token.cancel();
+
+                    if let Some(shared) = shared.upgrade() {
+                        shared.counters.job_timed_out();
+                        shared.emit(PoolEvent::JobTimedOut {
+                            worker_id: deadline.worker_id,
+                            job_name: deadline.job_name,
+                        });
+                    }
+                }
+
+                state = self.state.lock().unwrap();
+                continue;
+            }
+
+            let next: Option<Instant> = state.deadlines.values().map(|deadline| deadline.at).min();
+            state = match next {
+                Some(at) => self.changed.wait_timeout(state, at - now).unwrap().0,
+                None => self.changed.wait(state).unwrap(),
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        sync::{mpsc, Arc, Mutex},
+        thread,
+        time::Duration,
+    };
+
+    use crate::{CancelToken, PoolEvent, ThreadPool};
+
+    #[test]
+    fn test_default_job_timeout_cancels_cooperative_job() {
+        let events: Arc<Mutex<Vec<PoolEvent>>> = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&events);
+        let threadpool: ThreadPool = ThreadPool::builder()
+            .num_threads(1)
+            .default_job_timeout(Duration::from_millis(50))
+            .on_event(move |event| recorded.lock().unwrap().push(event.clone()))
+            .build()
+            .unwrap();
+
+        let (sender, receiver) = mpsc::channel();
+        threadpool
+            .execute_named("spin", move || {
+                let token: CancelToken = CancelToken::current().unwrap();
+                while !token.is_cancelled() {
+                    thread::sleep(Duration::from_millis(1));
+                }
+                sender.send(()).unwrap();
+            })
+            .unwrap();
+
+        receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        while threadpool.metrics().jobs_completed < 1 {
+            thread::yield_now();
+        }
+        assert_eq!(threadpool.metrics().jobs_timed_out, 1);
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![PoolEvent::JobTimedOut {
+                worker_id: 0,
+                job_name: Some("spin".to_string()),
+            }]
+        );
+
+        // A quick job is left alone.
+        assert!(!threadpool
+            .submit(|| CancelToken::current().unwrap().is_cancelled())
+            .join()
+            .unwrap());
+        assert_eq!(threadpool.metrics().jobs_timed_out, 1);
+    }
+}
@@ -0,0 +1,483 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    dispatch::{Inbox, Message},
+    job::Job,
+    os_thread::{self, WorkerThread},
+    priority, PoolEvent, RunningJobInfo, Shared,
+};
+
+pub(crate) struct Worker {
+    pub(crate) id: usize,
+    state: Arc<WorkerState>,
+}
+
+/// Per-worker state shared between the pool and the worker's thread.
+struct WorkerState {
+    /// Join handle of the worker's current thread. The thread installs its own replacement here
+    /// if it dies. A thread that exits normally hands back its [`Inbox`], if it had one.
+    thread: Mutex<Option<JoinHandle<Option<Inbox>>>>,
+    /// Set to make the thread exit once its current job is done.
+    retiring: AtomicBool,
+    /// Wall-clock time spent running jobs, in nanoseconds, across every thread of the worker.
+    busy_nanos: AtomicU64,
+}
+
+impl Worker {
+    /// Creates a new Worker.
+    ///
+    /// `id` - ID of  the worker.
+    ///
+    /// `shared` - [`Shared`] state holding the job queue and the pool's counters.
+    ///
+    /// `ready` - Notified once the thread has run `worker_init` and is about to wait for jobs.
+    ///
+    /// `inbox` - The worker's own channel under [`DequeueStrategy::PerWorkerChannel`], or
+    /// [`None`] to take jobs from the shared queue.
+    ///
+    /// # Panics
+    ///
+    /// This 'new' function will panic if the OS fails to spawn the thread.
+    ///
+    /// [`DequeueStrategy::PerWorkerChannel`]: crate::DequeueStrategy::PerWorkerChannel
+    pub(crate) fn new(
+        id: usize,
+        shared: Arc<Shared>,
+        ready: Option<mpsc::Sender<()>>,
+        inbox: Option<Inbox>,
+    ) -> Worker {
+        let state: Arc<WorkerState> = Arc::new(WorkerState {
+            thread: Mutex::new(None),
+            retiring: AtomicBool::new(false),
+            busy_nanos: AtomicU64::new(0),
+        });
+        Worker::spawn(id, shared, Arc::clone(&state), ready, inbox);
+
+        Worker { id, state }
+    }
+
+    /// Spawns the thread for worker `id` and stores its handle in `state`.
+    fn spawn(
+        id: usize,
+        shared: Arc<Shared>,
+        state: Arc<WorkerState>,
+        ready: Option<mpsc::Sender<()>>,
+        inbox: Option<Inbox>,
+    ) {
+        // Hold the slot while spawning so a replacement spawned by a quickly dying thread can't
+        // be overwritten by this handle.
+        let mut thread = state.thread.lock().unwrap();
+        shared.alive_workers.fetch_add(1, Ordering::SeqCst);
+        let thread_state: Arc<WorkerState> = Arc::clone(&state);
+
+        *thread = Some(thread::spawn(move || {
+            let mut sentinel: Sentinel = Sentinel {
+                id,
+                shared,
+                state: thread_state,
+                inbox,
+                initialized: false,
+                running_job: false,
+            };
+            sentinel.run(ready);
+            sentinel.inbox.take()
+        }));
+    }
+
+    /// Lets the worker finish its current job, then replaces its thread with a fresh one that
+    /// runs the `worker_init` hook again.
+    ///
+    /// A worker with its own channel first runs the jobs already dealt to it.
+    pub(crate) fn recycle(&self, shared: &Arc<Shared>) {
+        match shared.channels.read().unwrap().as_ref() {
+            Some(channels) => channels.stop(self.id),
+            None => {
+                self.retire();
+                shared.queue.wake_all();
+            }
+        }
+        let inbox: Option<Inbox> = self.join();
+
+        self.restart(shared, inbox);
+    }
+
+    /// Makes a worker that takes jobs from the shared queue exit after its current job, once the
+    /// queue is woken.
+    pub(crate) fn retire(&self) {
+        self.state.retiring.store(true, Ordering::SeqCst);
+    }
+
+    /// Spawns a fresh thread for a worker whose thread has been joined.
+    ///
+    /// * `inbox` - Channel the new thread takes jobs from, or [`None`] for the shared queue.
+    pub(crate) fn restart(&self, shared: &Arc<Shared>, inbox: Option<Inbox>) {
+        self.state.retiring.store(false, Ordering::SeqCst);
+        Worker::spawn(
+            self.id,
+            Arc::clone(shared),
+            Arc::clone(&self.state),
+            None,
+            inbox,
+        );
+    }
+
+    /// Wall-clock time the worker has spent running jobs.
+    pub(crate) fn busy_time(&self) -> Duration {
+        Duration::from_nanos(self.state.busy_nanos.load(Ordering::Relaxed))
+    }
+
+    /// Whether the worker's thread has exited or was abandoned.
+    pub(crate) fn is_finished(&self) -> bool {
+        self.state
+            .thread
+            .lock()
+            .unwrap()
+            .as_ref()
+            .is_none_or(JoinHandle::is_finished)
+    }
+
+    /// Stops tracking the worker's thread, leaving it to run on its own.
+    pub(crate) fn detach(&self) {
+        drop(self.state.thread.lock().unwrap().take());
+    }
+
+    /// Waits for the worker's thread, and any replacement spawned for it, to exit.
+    ///
+    /// # Returns
+    ///
+    /// The [`Inbox`] handed back by the last thread, if it had one.
+    pub(crate) fn join(&self) -> Option<Inbox> {
+        let mut inbox: Option<Inbox> = None;
+
+        loop {
+            let thread: Option<JoinHandle<Option<Inbox>>> =
+                self.state.thread.lock().unwrap().take();
+
+            match thread {
+                // A thread that panicked has already installed its replacement by the time the
+                // join returns, and handed its inbox to it.
+                Some(thread) => inbox = thread.join().unwrap_or(None),
+                None => return inbox,
+            }
+        }
+    }
+}
+
+/// Owns a worker thread's state and respawns the worker if the thread unwinds.
+///
+/// Under `panic = "abort"` a panicking job takes down the whole process before the sentinel is
+/// dropped, so there is nothing to respawn into.
+struct Sentinel {
+    id: usize,
+    shared: Arc<Shared>,
+    state: Arc<WorkerState>,
+    inbox: Option<Inbox>,
+    /// Whether `worker_init` has returned. A thread that panics before then isn't respawned,
+    /// since its replacement would most likely panic in the same way.
+    initialized: bool,
+    running_job: bool,
+}
+
+impl Sentinel {
+    fn run(&mut self, ready: Option<mpsc::Sender<()>>) {
+        let id: usize = self.id;
+        self.shared
+            .worker_threads
+            .lock()
+            .unwrap()
+            .insert(id, WorkerThread::current());
+
+        if let Err(err) = priority::apply(self.shared.thread_priority) {
+            self.shared.emit(PoolEvent::ThreadPriorityFailed {
+                worker_id: id,
+                error: err.to_string(),
+            });
+        }
+
+        if let Some(worker_init) = &self.shared.worker_init {
+            worker_init(id);
+        }
+        self.initialized = true;
+
+        if let Some(ready) = ready {
+            let _ = ready.send(());
+        }
+
+        loop {
+            let job: Option<Job> = self.next_job();
+
+            match job {
+                Some(job) => {
+                    self.shared
+                        .log(format_args!("Worker {id} got a job; executing."));
+                    self.shared.release_bytes(job.size);
+
+                    self.shared.counters.job_waited(job.queued_at.elapsed());
+                    self.start_job(job.name.clone());
+                    let panicked: bool = self.shared.run_task(id, job.name, job.task);
+                    self.finish_job(panicked);
+                }
+                None => {
+                    self.shared
+                        .log(format_args!("Worker {id} disconnected; shutting down."));
+                    break;
+                }
+            }
+        }
+    }
+}
+
+impl Sentinel {
+    /// Blocks until there's a job for this worker.
+    ///
+    /// # Returns
+    ///
+    /// The job, or [`None`] once the worker should exit.
+    fn next_job(&self) -> Option<Job> {
+        let Some(inbox) = &self.inbox else {
+            let fast_only: bool = self.id < self.shared.reserved_fast_workers;
+            return self.shared.queue.pop(fast_only, &self.state.retiring);
+        };
+
+        loop {
+            let message: Result<Message, mpsc::RecvError> = match inbox.try_recv() {
+                Ok(message) => Ok(message),
+                Err(mpsc::TryRecvError::Empty) => {
+                    // Only an idle worker takes the queue's mutex, to be counted as idle.
+                    self.shared.queue.enter_idle();
+                    let message = inbox.recv();
+                    self.shared.queue.leave_idle();
+                    message
+                }
+                Err(mpsc::TryRecvError::Disconnected) => Err(mpsc::RecvError),
+            };
+
+            match message {
+                Ok(Message::Job(job)) if self.shared.discard_queued.load(Ordering::SeqCst) => {
+                    self.shared.release_bytes(job.size);
+                    drop(job);
+                    self.shared.counters.jobs_discarded(1);
+                }
+                Ok(Message::Job(job)) => return Some(job),
+                Ok(Message::Stop) | Err(mpsc::RecvError) => return None,
+            }
+        }
+    }
+
+    /// Publishes the job this worker is about to run.
+    fn start_job(&mut self, job_name: Option<String>) {
+        self.shared.counters.job_started();
+        self.shared.running.lock().unwrap().insert(
+            self.id,
+            RunningJobInfo {
+                job_name,
+                started_at: Instant::now(),
+            },
+        );
+        self.running_job = true;
+    }
+
+    /// Clears the published job and reports how it went.
+    fn finish_job(&mut self, panicked: bool) {
+        self.running_job = false;
+        let info: Option<RunningJobInfo> = self.shared.running.lock().unwrap().remove(&self.id);
+
+        if let Some(info) = info {
+            let busy: u64 = u64::try_from(info.started_at.elapsed().as_nanos()).unwrap_or(u64::MAX);
+            self.state.busy_nanos.fetch_add(busy, Ordering::Relaxed);
+            self.shared
+                .job_finished(self.id, info.job_name, info.started_at, panicked);
+        }
+    }
+}
+
+impl Drop for Sentinel {
+    fn drop(&mut self) {
+        if thread::panicking() {
+            if self.running_job {
+                self.finish_job(true);
+            }
+
+            if !self.initialized {
+                self.shared.log(format_args!(
+                    "Worker {} panicked in worker_init; not respawning.",
+                    self.id
+                ));
+            } else if self.shared.respawn_workers {
+                self.shared
+                    .log(format_args!("Worker {} panicked; respawning.", self.id));
+                Worker::spawn(
+                    self.id,
+                    Arc::clone(&self.shared),
+                    Arc::clone(&self.state),
+                    None,
+                    self.inbox.take(),
+                );
+            } else {
+                self.shared
+                    .log(format_args!("Worker {} panicked; not respawning.", self.id));
+            }
+        }
+
+        // Still here only if no replacement took it over. Closed while holding the lock, so no job
+        // batch is being dealt to it meanwhile, but dropped without it, since dropping the jobs
+        // left inside can run code that submits more.
+        if let Some(inbox) = self.inbox.take() {
+            if let Some(channels) = self.shared.channels.write().unwrap().as_mut() {
+                channels.close(self.id);
+            }
+            drop(inbox);
+        }
+
+        let mut threads = self.shared.worker_threads.lock().unwrap();
+        // A replacement may have already recorded its own thread.
+        if threads
+            .get(&self.id)
+            .is_some_and(|thread| thread.os_id == os_thread::current_id())
+        {
+            threads.remove(&self.id);
+        }
+        drop(threads);
+
+        // The replacement, if any, was counted before this thread stops counting.
+        let exiting = self.shared.exiting.lock().unwrap();
+        self.shared.alive_workers.fetch_sub(1, Ordering::SeqCst);
+        drop(exiting);
+        self.shared.worker_exited.notify_all();
+    }
+}
+
+/// Work a worker thread runs: the boxed or recycled [`Task`](crate::job::Task) of a
+/// [`ThreadPool`](crate::ThreadPool) job, or the closure a
+/// [`TypedThreadPool`](crate::TypedThreadPool) queues as is.
+pub(crate) trait Runnable {
+    fn run(self);
+}
+
+impl<F: FnOnce()> Runnable for F {
+    fn run(self) {
+        self();
+    }
+}
+
+/// Runs `task`, containing any panic with `catch_unwind` when `catch_panics` is set.
+///
+/// Generic over [`Runnable`] so both kinds of pool share it, and a
+/// [`TypedThreadPool`](crate::TypedThreadPool) runs its jobs without boxing them.
+///
+/// # Returns
+///
+/// `true` if the job panicked.
+#[cfg(panic = "unwind")]
+pub(crate) fn run_job<R: Runnable>(task: R, catch_panics: bool) -> bool {
+    use std::panic::{self, AssertUnwindSafe};
+
+    if catch_panics {
+        return panic::catch_unwind(AssertUnwindSafe(|| task.run())).is_err();
+    }
+
+    task.run();
+    false
+}
+
+/// Runs `task`. `catch_unwind` can't stop a panic from aborting the process under
+/// `panic = "abort"`, so it isn't attempted.
+#[cfg(not(panic = "unwind"))]
+pub(crate) fn run_job<R: Runnable>(task: R, _catch_panics: bool) -> bool {
+    task.run();
+    false
+}
+
+#[cfg(all(test, panic = "unwind"))]
+mod test {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        thread,
+        time::Duration,
+    };
+
+    use crate::{Metrics, PoolError, ThreadPool};
+
+    #[test]
+    fn test_panicking_job_is_contained() {
+        let threadpool: ThreadPool = ThreadPool::new(1);
+
+        threadpool.execute(|| panic!("contained")).unwrap();
+
+        assert_eq!(threadpool.submit(|| 5).join().unwrap(), 5);
+        let metrics: Metrics = threadpool.metrics();
+        assert_eq!(metrics.jobs_panicked, 1);
+    }
+
+    #[test]
+    fn test_worker_respawns_after_uncaught_panic() {
+        let threadpool: ThreadPool = ThreadPool::builder()
+            .num_threads(1)
+            .catch_panics(false)
+            .build()
+            .unwrap_or_else(|_| panic!("failed to build pool"));
+
+        threadpool.execute(|| panic!("kills the worker")).unwrap();
+
+        assert_eq!(threadpool.submit(|| 7).join().unwrap(), 7);
+        assert_eq!(threadpool.metrics().jobs_panicked, 1);
+    }
+
+    #[test]
+    fn test_dead_pool_is_reported() {
+        let threadpool: ThreadPool = ThreadPool::builder()
+            .num_threads(2)
+            .catch_panics(false)
+            .respawn_workers(false)
+            .reject_if_no_workers(true)
+            .build()
+            .unwrap();
+
+        assert_eq!(threadpool.assert_workers_alive(), Ok(()));
+        for _ in 0..2 {
+            threadpool.execute(|| panic!("kills a worker")).unwrap();
+        }
+
+        while threadpool.assert_workers_alive().is_ok() {
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(threadpool.assert_workers_alive(), Err(PoolError::NoWorkers));
+        assert_eq!(threadpool.execute(|| ()), Err(PoolError::NoWorkers));
+    }
+
+    #[test]
+    fn test_worker_init_panic_is_not_respawned() {
+        let inits: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+        let init_count = Arc::clone(&inits);
+        let threadpool: ThreadPool = ThreadPool::builder()
+            .num_threads(2)
+            .worker_init(move |id| {
+                if id == 0 {
+                    init_count.fetch_add(1, Ordering::SeqCst);
+                    panic!("worker_init failed");
+                }
+            })
+            .prewarm(true)
+            .build()
+            .unwrap();
+
+        while threadpool.shared.alive_workers.load(Ordering::SeqCst) > 1 {
+            thread::sleep(Duration::from_millis(1));
+        }
+        thread::sleep(Duration::from_millis(50));
+
+        assert_eq!(inits.load(Ordering::SeqCst), 1);
+        assert_eq!(threadpool.shared.alive_workers.load(Ordering::SeqCst), 1);
+        assert_eq!(threadpool.submit(|| 3).join().unwrap(), 3);
+    }
+}
@@ -0,0 +1,120 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc, Arc, Mutex,
+    },
+};
+
+use copper_line::{ThreadPool, TypedThreadPool};
+
+/// Counts the allocations made by each thread, so tests running in parallel don't skew them.
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+// SAFETY: forwards every call to the system allocator unchanged.
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+// Installed for this test binary alone, so the library's unit tests keep the system allocator.
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// # Returns
+///
+/// Allocations the calling thread made while running `f`.
+fn allocations_during(f: impl FnOnce()) -> usize {
+    let before: usize = ALLOCATIONS.with(Cell::get);
+    f();
+    ALLOCATIONS.with(Cell::get) - before
+}
+
+/// Submits `count` small jobs after a warm-up round, once the pool is idle.
+///
+/// Both rounds queue every job behind blocked workers, so the warm-up leaves a recycled
+/// allocation for each job of the measured round however busy the machine is.
+///
+/// # Returns
+///
+/// Allocations the submitting thread made for the measured jobs.
+fn allocations_for_jobs(recycle_job_boxes: bool, count: usize) -> usize {
+    let threadpool: ThreadPool = ThreadPool::builder()
+        .num_threads(2)
+        .recycle_job_boxes(recycle_job_boxes)
+        .build()
+        .unwrap();
+    let total: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+    let submit = || {
+        let (release, released) = mpsc::channel::<()>();
+        let released: Arc<Mutex<mpsc::Receiver<()>>> = Arc::new(Mutex::new(released));
+        for _ in 0..2 {
+            let released = Arc::clone(&released);
+            threadpool
+                .execute(move || {
+                    let _ = released.lock().unwrap().recv();
+                })
+                .unwrap();
+        }
+
+        for _ in 0..count {
+            let total = Arc::clone(&total);
+            threadpool
+                .execute(move || {
+                    total.fetch_add(1, Ordering::Relaxed);
+                })
+                .unwrap();
+        }
+        drop(release);
+        threadpool.wait_for_idle_workers(2);
+    };
+
+    submit();
+    let allocations: usize = allocations_during(submit);
+
+    assert_eq!(total.load(Ordering::Relaxed), count * 2);
+    allocations
+}
+
+#[test]
+fn test_recycled_job_boxes_avoid_allocations() {
+    let plain: usize = allocations_for_jobs(false, 200);
+    let recycled: usize = allocations_for_jobs(true, 200);
+
+    assert!(plain >= 200, "{plain} allocations without recycling");
+    assert!(recycled < 20, "{recycled} allocations with recycling");
+}
+
+#[test]
+fn test_typed_pool_queues_jobs_without_boxing() {
+    static TOTAL: AtomicUsize = AtomicUsize::new(0);
+    let threadpool = TypedThreadPool::new(2);
+    // Every job is the same closure type, so they're queued as they are.
+    let submit = |count: usize| {
+        for i in 0..count {
+            threadpool.execute(move || {
+                TOTAL.fetch_add(i, Ordering::Relaxed);
+            });
+        }
+    };
+
+    // Lets the queue grow to its working size first.
+    submit(1_000);
+    let allocations: usize = allocations_during(|| submit(1_000));
+    drop(threadpool);
+
+    assert_eq!(TOTAL.load(Ordering::Relaxed), 2 * (0..1_000).sum::<usize>());
+    // Only the queue growing further, if the workers fall behind, allocates.
+    assert!(allocations < 20, "{allocations} allocations for 1000 jobs");
+}